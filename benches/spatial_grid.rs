@@ -0,0 +1,81 @@
+use bevy::prelude::*;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use rand::Rng;
+
+#[allow(dead_code)]
+#[path = "../src/spatial.rs"]
+mod spatial;
+
+use spatial::{GridEntry, GridKind, SpatialGrid};
+
+const VISION: f32 = 20.;
+// roughly the density of a crowded sheep spawn
+const AREA_PER_SHEEP: f32 = 16.;
+
+fn flock(count: usize) -> Vec<GridEntry> {
+    let mut rng = rand::thread_rng();
+    let side = (count as f32 * AREA_PER_SHEEP).sqrt();
+
+    (0..count)
+        .map(|i| GridEntry {
+            entity: Entity::from_raw(i as u32),
+            kind: GridKind::Sheep,
+            position: Vec2::new(rng.gen_range(0.0..side), rng.gen_range(0.0..side)),
+            velocity: Vec2::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)),
+            weight: 1.,
+        })
+        .collect()
+}
+
+fn neighbours_grid(grid: &mut SpatialGrid, sheeps: &[GridEntry]) -> usize {
+    grid.clear(VISION);
+    sheeps.iter().for_each(|sheep| grid.insert(*sheep));
+
+    sheeps
+        .iter()
+        .map(|sheep| {
+            grid.query_kind(sheep.position, VISION, GridKind::Sheep)
+                .filter(|other| other.entity != sheep.entity)
+                .count()
+        })
+        .sum()
+}
+
+fn neighbours_brute_force(sheeps: &[GridEntry]) -> usize {
+    sheeps
+        .iter()
+        .map(|sheep| {
+            sheeps
+                .iter()
+                .filter(|other| {
+                    other.entity != sheep.entity
+                        && other.position.distance_squared(sheep.position) <= VISION * VISION
+                })
+                .count()
+        })
+        .sum()
+}
+
+fn flocking_neighbours(c: &mut Criterion) {
+    let mut group = c.benchmark_group("flocking_neighbours");
+
+    for count in [500, 1000, 2500, 5000] {
+        let sheeps = flock(count);
+        let mut grid = SpatialGrid::new(VISION);
+
+        group.bench_with_input(BenchmarkId::new("grid", count), &sheeps, |b, sheeps| {
+            b.iter(|| neighbours_grid(&mut grid, black_box(sheeps)))
+        });
+
+        group.bench_with_input(
+            BenchmarkId::new("brute_force", count),
+            &sheeps,
+            |b, sheeps| b.iter(|| neighbours_brute_force(black_box(sheeps))),
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, flocking_neighbours);
+criterion_main!(benches);
@@ -1,16 +1,40 @@
+use std::time::Duration;
+
 use bevy::{gltf::Gltf, prelude::*};
 use bevy_rapier2d::dynamics::Velocity;
+use serde::Deserialize;
 
 use super::llama::JumpTag;
+use crate::simulation::SimulationSet;
 
 pub struct AnimalAnimationPlugin;
 impl Plugin for AnimalAnimationPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, (switch_animation, jump_once, update_state));
+        // stamina reads the state, so it has to advance with the simulation
+        app.add_systems(FixedUpdate, update_state.in_set(SimulationSet::Prepare));
+        app.add_systems(
+            Update,
+            (
+                link_animation_player,
+                switch_animation,
+                jump_once,
+                scale_animation_speed,
+            )
+                .chain(),
+        );
     }
 }
 
-#[derive(Component, Default)]
+const TRANSITION: Duration = Duration::from_millis(250);
+// movement speed at which the walk and run clips play at their natural pace
+const WALK_REFERENCE_SPEED: f32 = 4.;
+const RUN_REFERENCE_SPEED: f32 = 16.;
+
+/// The `AnimationPlayer` somewhere down the spawned scene, found once.
+#[derive(Component)]
+pub struct AnimationPlayerLink(Entity);
+
+#[derive(Component, Default, Clone, Copy, PartialEq, Eq)]
 pub enum AnimalState {
     #[default]
     Idle,
@@ -45,6 +69,41 @@ impl AnimalState {
     }
 }
 
+/// Clip names in the model's gltf, for each animal state.
+#[derive(Component, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct AnimationNames {
+    pub idle: String,
+    pub walk: String,
+    pub run: String,
+    pub jump: String,
+    pub death: String,
+}
+
+impl Default for AnimationNames {
+    fn default() -> Self {
+        Self {
+            idle: "Idle".into(),
+            walk: "Walk".into(),
+            run: "Run".into(),
+            jump: "Jump".into(),
+            death: "Death".into(),
+        }
+    }
+}
+
+impl AnimationNames {
+    pub fn get(&self, state: &AnimalState) -> &str {
+        match state {
+            AnimalState::Idle => &self.idle,
+            AnimalState::Dead => &self.death,
+            AnimalState::Walking => &self.walk,
+            AnimalState::Running => &self.run,
+            AnimalState::Jumping => &self.jump,
+        }
+    }
+}
+
 fn update_state(mut query: Query<(&Velocity, &mut AnimalState)>) {
     query.iter_mut().for_each(|(velocity, mut state)| {
         if state.is_dead() {
@@ -53,44 +112,67 @@ fn update_state(mut query: Query<(&Velocity, &mut AnimalState)>) {
 
         let speed = velocity.linvel.length();
 
-        if speed > 10. {
-            *state = AnimalState::Running;
-            return;
-        }
+        // only touch the state on an actual change, a one-shot clip like
+        // the llama jump would get cut off otherwise
+        let next = match speed {
+            s if s > 10. => AnimalState::Running,
+            s if s > 1.0 => AnimalState::Walking,
+            _ => AnimalState::Idle,
+        };
 
-        if speed > 1.0 {
-            *state = AnimalState::Walking;
-            return;
-        }
+        state.set_if_neq(next);
+    });
+}
 
-        *state = AnimalState::Idle;
+fn link_animation_player(
+    mut cmd: Commands,
+    query: Query<Entity, (With<Handle<Gltf>>, Without<AnimationPlayerLink>)>,
+    children: Query<&Children>,
+    players: Query<&AnimationPlayer>,
+) {
+    // the scene spawns a few frames after the animal, so keep looking until it's there
+    query.iter().for_each(|entity| {
+        if let Some(player) = find_child_rec(entity, &children, &players) {
+            cmd.entity(entity).insert(AnimationPlayerLink(player));
+        }
     });
 }
 
 fn switch_animation(
     mut animation_player: Query<&mut AnimationPlayer>,
-    query: Query<(Entity, &Handle<Gltf>, &AnimalState), Changed<AnimalState>>,
-    children: Query<&Children>,
-    _server: Res<AssetServer>,
+    query: Query<
+        (
+            &Handle<Gltf>,
+            &AnimalState,
+            &AnimationPlayerLink,
+            Option<&AnimationNames>,
+        ),
+        Or<(Changed<AnimalState>, Added<AnimationPlayerLink>)>,
+    >,
     gltf_assets: Res<Assets<Gltf>>,
 ) {
-    query.iter().for_each(|(entity, gltf, state)| {
+    query.iter().for_each(|(gltf, state, link, names)| {
         let Some(gltf) = gltf_assets.get(gltf) else {
             return;
         };
 
-        let Some(ent) = find_child_rec(entity, &children, &animation_player.to_readonly()) else {
+        let Ok(mut player) = animation_player.get_mut(link.0) else {
             return;
         };
 
-        let mut player = animation_player.get_mut(ent).unwrap();
-
-        let Some(clip) = gltf.named_animations.get(state.animation_name()) else {
+        let name = names.map_or(state.animation_name(), |names| names.get(state));
+        let Some(clip) = gltf.named_animations.get(name) else {
             return;
         };
 
-        if player.animation_clip() != clip {
-            player.play(clip.clone()).set_speed(2.).repeat();
+        if player.animation_clip() == clip {
+            return;
+        }
+
+        let player = player.play_with_transition(clip.clone(), TRANSITION);
+        // dying only happens once
+        if !state.is_dead() {
+            player.repeat();
         }
     });
 }
@@ -98,23 +180,28 @@ fn switch_animation(
 fn jump_once(
     mut cmd: Commands,
     mut animation_player: Query<&mut AnimationPlayer>,
-    query: Query<(Entity, &Handle<Gltf>), With<JumpTag>>,
-    children: Query<&Children>,
-    _server: Res<AssetServer>,
+    query: Query<
+        (
+            Entity,
+            &Handle<Gltf>,
+            &AnimationPlayerLink,
+            Option<&AnimationNames>,
+        ),
+        With<JumpTag>,
+    >,
     gltf_assets: Res<Assets<Gltf>>,
 ) {
-    query.iter().for_each(|(entity, gltf)| {
+    query.iter().for_each(|(entity, gltf, link, names)| {
         let Some(gltf) = gltf_assets.get(gltf) else {
             return;
         };
 
-        let Some(ent) = find_child_rec(entity, &children, &animation_player.to_readonly()) else {
+        let Ok(mut player) = animation_player.get_mut(link.0) else {
             return;
         };
 
-        let mut player = animation_player.get_mut(ent).unwrap();
-
-        let Some(clip) = gltf.named_animations.get("Jump") else {
+        let name = names.map_or("Jump", |names| names.jump.as_str());
+        let Some(clip) = gltf.named_animations.get(name) else {
             return;
         };
 
@@ -123,6 +210,38 @@ fn jump_once(
     });
 }
 
+/// Plays walk and run cycles as fast as the animal actually moves, so the
+/// feet don't slide over the ground.
+fn scale_animation_speed(
+    mut animation_player: Query<&mut AnimationPlayer>,
+    query: Query<(
+        &AnimalState,
+        &AnimationPlayerLink,
+        &Transform,
+        Option<&Velocity>,
+    )>,
+) {
+    query.iter().for_each(|(state, link, transform, velocity)| {
+        let Ok(mut player) = animation_player.get_mut(link.0) else {
+            return;
+        };
+
+        let speed = velocity.map_or(0., |velocity| velocity.linvel.length())
+            / transform.scale.x.max(f32::EPSILON);
+
+        let playback = match state {
+            AnimalState::Walking => (speed / WALK_REFERENCE_SPEED).clamp(0.5, 3.),
+            AnimalState::Running => (speed / RUN_REFERENCE_SPEED).clamp(0.5, 3.),
+            AnimalState::Idle => 1.,
+            AnimalState::Jumping | AnimalState::Dead => 2.,
+        };
+
+        if player.speed() != playback {
+            player.set_speed(playback);
+        }
+    });
+}
+
 pub fn find_child_rec(
     current: Entity,
     children_query: &Query<&Children>,
@@ -132,13 +251,10 @@ pub fn find_child_rec(
         return None;
     };
 
-    for child in children.iter() {
-        if aniplayer.get(*child).is_ok() {
-            return Some(*child);
-        } else {
-            return find_child_rec(*child, children_query, aniplayer);
-        }
-    }
-
-    None
+    children
+        .iter()
+        .find_map(|child| match aniplayer.contains(*child) {
+            true => Some(*child),
+            false => find_child_rec(*child, children_query, aniplayer),
+        })
 }
@@ -1,30 +1,148 @@
+use std::{collections::VecDeque, time::Duration};
+
 use bevy::{gltf::Gltf, prelude::*};
 
 use bevy_rapier2d::{
-    dynamics::{Damping, RigidBody, Velocity},
-    geometry::{Collider, ColliderMassProperties},
+    dynamics::{Damping, ExternalImpulse, RigidBody, Velocity},
+    geometry::{Collider, ColliderMassProperties, CollisionGroups},
+};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    animations::AnimalState,
+    physics::{MoveTo, Stamina},
+    sheep::Temperament,
+    species::BehaviorWeights,
+    telegraph::{TelegraphBundle, TelegraphMaterial},
+};
+use crate::{
+    collision,
+    controls::{play_dog_sound, DogSound},
+    level::{
+        loader::{AnimalBehavior, LevelAsset},
+        tilemap::TileMap,
+        TILE_SIZE,
+    },
+    selection::{DogIndex, Selected},
+    settings::GameSettings,
+    simulation::{Interpolated, SimulationSet},
+    spatial::{GridKind, SpatialGrid},
+    state::{AllowedState, GameState},
+    util::{Cooldown, LifeTime},
 };
 
-use super::{animations::AnimalState, physics::MoveTo};
-use crate::{state::{AllowedState, GameState}, level::loader::LevelAsset};
+pub const BARK_RING_MESH: Handle<Mesh> = Handle::weak_from_u128(73920461582093475617203);
+
+const ROUTE_COLOR: Color = Color::rgb(1.0, 0.9, 0.2);
+const BARK_RING_DURATION: Duration = Duration::from_millis(300);
+// stamina an exhausted dog needs before it can sprint again
+const SPRINT_RECOVERY: f32 = 0.3;
 
 pub struct DogPlugin;
 impl Plugin for DogPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, move_dogs);
+        app.add_event::<BarkEvent>();
+
+        let mut meshes = app.world.get_resource_mut::<Assets<Mesh>>().unwrap();
+        meshes.insert(
+            BARK_RING_MESH,
+            Mesh::from(shape::Quad::new(Vec2::splat(2.))),
+        );
+        app.add_systems(
+            FixedUpdate,
+            (
+                update_dog_stamina,
+                update_dog_paths,
+                apply_deferred,
+                move_dogs,
+                steer_dogs,
+                bark.run_if(on_event::<BarkEvent>()),
+            )
+                .chain()
+                .in_set(SimulationSet::Gameplay),
+        );
+        app.add_systems(Update, draw_routes.run_if(in_state(GameState::Game)));
+        app.add_systems(Update, expand_bark_rings.run_if(in_state(GameState::Game)));
     }
 }
 
 #[derive(Component)]
 pub struct DogTag;
 
+/// How the dog moves, trading speed against the pressure it puts on the flock.
+#[derive(Component, Default, Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum DogGait {
+    #[default]
+    Normal,
+    Sprint,
+    Crouch,
+}
+
+impl DogGait {
+    pub fn speed(&self, animal_behavior: &AnimalBehavior, stamina: &Stamina) -> f32 {
+        animal_behavior.dog_speed
+            * match self {
+                Self::Sprint if !stamina.is_exhausted() => animal_behavior.sprint_speed,
+                Self::Crouch => animal_behavior.crouch_speed,
+                _ => 1.,
+            }
+    }
+
+    /// How much sheep fear this dog, compared to a normally moving one.
+    pub fn fear(&self, animal_behavior: &AnimalBehavior) -> f32 {
+        match self {
+            Self::Crouch => animal_behavior.crouch_fear,
+            _ => 1.,
+        }
+    }
+}
+
+/// Waypoints around walls and traps towards the current `MoveTo`.
+#[derive(Component, Default)]
+pub struct Path(VecDeque<Vec2>);
+
+/// Shift-clicked targets the dog heads for once it reached its `MoveTo`.
+#[derive(Component, Default)]
+pub struct Waypoints(VecDeque<Vec2>);
+
+impl Waypoints {
+    pub fn push(&mut self, target: Vec2) {
+        self.0.push_back(target);
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+/// Direction held on the keyboard, overrides any click target while set.
+#[derive(Component, Default)]
+pub struct Steering(pub Vec2);
+
+#[derive(Event)]
+pub struct BarkEvent {
+    pub dog: Entity,
+}
+
+#[derive(Component)]
+struct BarkRing(Timer);
+
 #[derive(Bundle)]
 pub struct DogBundle {
     pub body: RigidBody,
     pub collider: Collider,
+    pub collision_groups: CollisionGroups,
+    pub interpolated: Interpolated,
     pub scene: Handle<Scene>,
     pub gltf: Handle<Gltf>,
     pub dog_tag: DogTag,
+    pub weights: BehaviorWeights,
+    pub gait: DogGait,
+    pub stamina: Stamina,
+    pub waypoints: Waypoints,
+    pub steering: Steering,
+    pub index: DogIndex,
+    pub selected: Selected,
     pub state: AnimalState,
     pub velocity: Velocity,
     pub visibility: Visibility,
@@ -43,11 +161,20 @@ impl Default for DogBundle {
         Self {
             body: RigidBody::Dynamic,
             collider: Collider::ball(2.),
+            collision_groups: collision::dog(),
+            interpolated: Interpolated::default(),
             velocity: Velocity::default(),
             state: AnimalState::Idle,
             scene: Handle::default(),
             gltf: Handle::default(),
             dog_tag: DogTag,
+            weights: BehaviorWeights::default(),
+            gait: DogGait::default(),
+            stamina: Stamina::default(),
+            waypoints: Waypoints::default(),
+            steering: Steering::default(),
+            index: DogIndex::default(),
+            selected: Selected,
             visibility: Visibility::Inherited,
             inherited_visibility: InheritedVisibility::HIDDEN,
             view_visibility: ViewVisibility::HIDDEN,
@@ -64,15 +191,111 @@ impl Default for DogBundle {
     }
 }
 
+fn update_dog_paths(
+    mut cmd: Commands,
+    dogs: Query<(Entity, &Transform, &MoveTo), (With<DogTag>, Changed<MoveTo>)>,
+    tilemap: Res<TileMap>,
+) {
+    dogs.iter().for_each(|(entity, transform, move_to)| {
+        let path = tilemap.find_path(transform.translation.truncate(), move_to.postion());
+        cmd.entity(entity).insert(Path(path.into()));
+    });
+}
+
 fn move_dogs(
     mut cmd: Commands,
-    mut query: Query<(Entity, &mut Velocity, &MoveTo, &Transform), With<DogTag>>,
+    mut query: Query<
+        (
+            Entity,
+            &mut Velocity,
+            &mut MoveTo,
+            &mut Path,
+            &mut Waypoints,
+            &Transform,
+            &DogGait,
+            &Stamina,
+            &BehaviorWeights,
+        ),
+        With<DogTag>,
+    >,
     levels: Res<Assets<LevelAsset>>,
     level: Query<&Handle<LevelAsset>>,
-    _time: Res<Time>,
 ) {
+    let Ok(handle) = level.get_single() else {
+        debug!("wtf you doing");
+        return;
+    };
 
+    let Some(level) = levels.get(handle) else {
+        return;
+    };
 
+    let animal_behavior = level.animal_behavior.as_ref().unwrap_or_default();
+
+    query.iter_mut().for_each(
+        |(
+            entity,
+            mut velocity,
+            mut move_to,
+            mut path,
+            mut waypoints,
+            transform,
+            gait,
+            stamina,
+            weights,
+        )| {
+            let position = transform.translation.truncate();
+
+            // skip waypoints already reached, the last one has to be hit exactly
+            while path.0.len() > 1
+                && path
+                    .0
+                    .front()
+                    .is_some_and(|waypoint| waypoint.distance(position) < TILE_SIZE / 2.)
+            {
+                path.0.pop_front();
+            }
+
+            let Some(waypoint) = path.0.front().copied() else {
+                match waypoints.0.pop_front() {
+                    Some(next) => {
+                        move_to.set(next);
+                    }
+                    None => {
+                        cmd.entity(entity).remove::<(MoveTo, Path)>();
+                    }
+                }
+                return;
+            };
+
+            let direction = waypoint - position;
+
+            if direction.length() < 2.0 {
+                path.0.pop_front();
+                return;
+            }
+
+            velocity.linvel = direction.normalize_or_zero()
+                * gait.speed(animal_behavior, stamina)
+                * weights.speed;
+        },
+    );
+}
+
+fn steer_dogs(
+    mut query: Query<
+        (
+            &mut Velocity,
+            &Steering,
+            &DogGait,
+            &Stamina,
+            &BehaviorWeights,
+        ),
+        With<DogTag>,
+    >,
+    levels: Res<Assets<LevelAsset>>,
+    level: Query<&Handle<LevelAsset>>,
+) {
     let Ok(handle) = level.get_single() else {
         debug!("wtf you doing");
         return;
@@ -84,26 +307,160 @@ fn move_dogs(
 
     let animal_behavior = level.animal_behavior.as_ref().unwrap_or_default();
 
-
     query
         .iter_mut()
-        .for_each(|(entity, mut velocity, move_to, transform)| {
-            let direction = move_to.postion() - transform.translation.truncate();
-            let distance = direction.length();
+        .filter(|(_, steering, ..)| steering.0 != Vec2::ZERO)
+        .for_each(|(mut velocity, steering, gait, stamina, weights)| {
+            velocity.linvel = steering.0 * gait.speed(animal_behavior, stamina) * weights.speed;
+        });
+}
 
-            if distance < 2.0 {
-                cmd.entity(entity).remove::<MoveTo>();
-                return;
-            }
+fn draw_routes(
+    mut gizmos: Gizmos,
+    dogs: Query<(&Transform, Option<&Path>, &Waypoints), With<DogTag>>,
+) {
+    dogs.iter().for_each(|(transform, path, waypoints)| {
+        if waypoints.0.is_empty() {
+            return;
+        }
+
+        let lift = |point: Vec2| point.extend(0.2);
+
+        // the queued legs aren't routed yet, so those are drawn straight
+        let route = std::iter::once(transform.translation.truncate())
+            .chain(path.iter().flat_map(|path| path.0.iter().copied()))
+            .chain(waypoints.0.iter().copied())
+            .map(lift);
+
+        gizmos.linestrip(route, ROUTE_COLOR);
+
+        waypoints.0.iter().for_each(|waypoint| {
+            gizmos.circle(lift(*waypoint), Vec3::Z, 1.5, ROUTE_COLOR);
+        });
+    });
+}
+
+fn update_dog_stamina(
+    mut dogs: Query<(&DogGait, &AnimalState, &mut Stamina), With<DogTag>>,
+    levels: Res<Assets<LevelAsset>>,
+    level: Query<&Handle<LevelAsset>>,
+    time: Res<Time>,
+) {
+    let Ok(handle) = level.get_single() else {
+        return;
+    };
+
+    let Some(level) = levels.get(handle) else {
+        return;
+    };
+
+    let animal_behavior = level.animal_behavior.as_ref().unwrap_or_default();
+    let delta = time.delta_seconds();
 
-            velocity.linvel= direction.normalize_or_zero() * animal_behavior.dog_speed;
-            // velocity.linvel = (velocity.linvel.normalize_or_zero()
-            //     + direction.normalize_or_zero()
-            //         * time.delta_seconds()
-            //         * velocity.linvel.length().max(1.)
-            //         / DOG_TURN_SPEED)
-            //     .normalize_or_zero()
-            //     * animal_behavior.speed
-            //     * 3.;
+    dogs.iter_mut()
+        .for_each(|(gait, state, mut stamina)| match (gait, state) {
+            (DogGait::Sprint, AnimalState::Running) => {
+                stamina.drain(animal_behavior.sprint_drain * delta)
+            }
+            _ => stamina.recover(animal_behavior.sprint_regen * delta, SPRINT_RECOVERY),
         });
 }
+
+fn bark(
+    mut cmd: Commands,
+    mut barks: EventReader<BarkEvent>,
+    dogs: Query<&Transform, (With<DogTag>, Without<Cooldown>)>,
+    sheeps: Query<(&Temperament, &Stamina)>,
+    grid: Res<SpatialGrid>,
+    levels: Res<Assets<LevelAsset>>,
+    level: Query<&Handle<LevelAsset>>,
+    dog_sounds: Query<With<DogSound>>,
+    server: Res<AssetServer>,
+    volume: Res<GameSettings>,
+    mut materials: ResMut<Assets<TelegraphMaterial>>,
+) {
+    let Ok(handle) = level.get_single() else {
+        debug!("wtf you doing");
+        return;
+    };
+
+    let Some(level) = levels.get(handle) else {
+        return;
+    };
+
+    let animal_behavior = level.animal_behavior.as_ref().unwrap_or_default();
+    let radius = animal_behavior.bark_radius;
+    let mut sound_playing = dog_sounds.iter().count() > 0;
+
+    barks.read().for_each(|event| {
+        let Ok(transform) = dogs.get(event.dog) else {
+            return;
+        };
+
+        let position = transform.translation.truncate();
+
+        grid.query_kind(position, radius, GridKind::Sheep)
+            .for_each(|sheep| {
+                let fear = match sheeps.get(sheep.entity) {
+                    // too tired to care, just like with the dog itself
+                    Ok((_, stamina)) if stamina.is_exhausted() => return,
+                    Ok((temperament, _)) => temperament.fear,
+                    Err(_) => 1.,
+                };
+
+                let away = sheep.position - position;
+                let falloff = 1. - away.length() / radius.max(f32::EPSILON);
+
+                // startled for a moment, so the flocking doesn't eat the impulse right away
+                cmd.entity(sheep.entity)
+                    .insert(Cooldown::new(Duration::from_secs_f32(
+                        animal_behavior.bark_startle,
+                    )))
+                    .insert(ExternalImpulse {
+                        impulse: away.normalize_or_zero()
+                            * animal_behavior.bark_strength
+                            * falloff
+                            * fear,
+                        ..default()
+                    });
+            });
+
+        cmd.entity(event.dog)
+            .insert(Cooldown::new(Duration::from_secs_f32(
+                animal_behavior.bark_cooldown,
+            )));
+
+        cmd.spawn(TelegraphBundle {
+            mesh: BARK_RING_MESH,
+            material: materials.add(TelegraphMaterial {
+                progress: Vec4::ZERO,
+                color: Color::WHITE,
+            }),
+            transform: Transform::from_translation(position.extend(0.1))
+                .with_scale(Vec3::splat(radius)),
+            ..default()
+        })
+        .insert(BarkRing(Timer::new(BARK_RING_DURATION, TimerMode::Once)))
+        .insert(LifeTime::new(BARK_RING_DURATION))
+        .insert(AllowedState::new(GameState::Game));
+
+        if !sound_playing {
+            play_dog_sound(&mut cmd, &server, &volume);
+            sound_playing = true;
+        }
+    });
+}
+
+fn expand_bark_rings(
+    mut rings: Query<(&mut BarkRing, &Handle<TelegraphMaterial>)>,
+    mut materials: ResMut<Assets<TelegraphMaterial>>,
+    time: Res<Time>,
+) {
+    rings.iter_mut().for_each(|(mut ring, material)| {
+        let Some(material) = materials.get_mut(material) else {
+            return;
+        };
+
+        material.progress.x = ring.0.tick(time.delta()).percent();
+    });
+}
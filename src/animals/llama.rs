@@ -2,29 +2,48 @@ use std::time::Duration;
 
 use bevy::{gltf::Gltf, prelude::*};
 use bevy_rapier2d::{
-    dynamics::ExternalImpulse, geometry::Collider, pipeline::QueryFilter, plugin::RapierContext,
+    dynamics::{Damping, ExternalImpulse, RigidBody, Velocity},
+    geometry::{Collider, ColliderMassProperties, CollisionGroups},
 };
 use bevy_tweening::{
     lens::TransformPositionLens, Animator, EaseFunction, RepeatCount, RepeatStrategy, Tween,
 };
+use rand::Rng;
 
 use crate::{
-    level::loader::LevelAsset,
+    collision,
+    level::{
+        loader::{LevelAsset, LlamaRoute},
+        tilemap::TileMap,
+        TILE_SIZE,
+    },
+    simulation::{Interpolated, SimulationRng, SimulationSet},
+    spatial::{GridKind, SpatialGrid},
     state::{AllowedState, GameState},
     util::Cooldown,
 };
 
 use super::{
-    sheep::SheepTag,
+    animations::AnimalState,
+    sheep::roll_temperament,
+    species::BehaviorWeights,
     telegraph::{TelegraphBundle, TelegraphMaterial, TelegraphTag},
 };
 pub struct LlamaPlugin;
 impl Plugin for LlamaPlugin {
     fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            // draws from the shared rng, keep it in the same order every tick
+            (move_llamas, llama_stomp)
+                .chain()
+                .after(roll_temperament)
+                .run_if(in_state(GameState::Game))
+                .in_set(SimulationSet::Gameplay),
+        );
         app.add_systems(
             Update,
-            (llama_stomp, add_telegraph_to_llama, update_progress)
-                .run_if(in_state(GameState::Game)),
+            (add_telegraph_to_llama, update_progress).run_if(in_state(GameState::Game)),
         );
     }
 }
@@ -35,11 +54,66 @@ pub struct LLamaTag;
 #[derive(Component)]
 pub struct JumpTag;
 
+/// Waits for sheep in range, winds up while the telegraph fills, then stomps.
+#[derive(Component, Default)]
+pub enum LlamaState {
+    #[default]
+    Idle,
+    Charging(Timer),
+    Stomping(Timer),
+}
+
+#[derive(Component, Default)]
+pub enum LlamaMovement {
+    #[default]
+    Stationary,
+    Patrol {
+        waypoints: Vec<Vec2>,
+        next: usize,
+    },
+    Wander {
+        center: Vec2,
+        radius: f32,
+        target: Option<Vec2>,
+    },
+}
+
+impl LlamaMovement {
+    pub fn from_route(route: &LlamaRoute, spawn: Vec2) -> Self {
+        match route {
+            LlamaRoute::Stationary => Self::Stationary,
+            LlamaRoute::Patrol(waypoints) => Self::Patrol {
+                waypoints: waypoints
+                    .iter()
+                    .map(|(line, column)| Vec2::new(*line, *column) * TILE_SIZE)
+                    .collect(),
+                next: 0,
+            },
+            LlamaRoute::Wander(radius) => Self::Wander {
+                center: spawn,
+                radius: radius * TILE_SIZE,
+                target: None,
+            },
+        }
+    }
+}
+
 #[derive(Bundle)]
 pub struct LLamaBundle {
+    pub body: RigidBody,
+    pub collider: Collider,
+    pub collision_groups: CollisionGroups,
+    pub interpolated: Interpolated,
+    pub velocity: Velocity,
+    pub damping: Damping,
+    pub mass: ColliderMassProperties,
+    pub state: AnimalState,
+    pub llama_state: LlamaState,
+    pub movement: LlamaMovement,
     pub scene: Handle<Scene>,
     pub gltf: Handle<Gltf>,
     pub llama_tag: LLamaTag,
+    pub weights: BehaviorWeights,
     pub visibility: Visibility,
     pub inherited_visibility: InheritedVisibility,
     pub view_visibility: ViewVisibility,
@@ -51,9 +125,24 @@ pub struct LLamaBundle {
 impl Default for LLamaBundle {
     fn default() -> Self {
         Self {
+            body: RigidBody::Dynamic,
+            collider: Collider::ball(3.),
+            collision_groups: collision::predator(),
+            interpolated: Interpolated::default(),
+            velocity: Velocity::default(),
+            damping: Damping {
+                linear_damping: 3.,
+                angular_damping: 1.,
+            },
+            // heavy enough that the flock can't push it around
+            mass: ColliderMassProperties::Mass(80.),
+            state: AnimalState::Idle,
+            llama_state: LlamaState::default(),
+            movement: LlamaMovement::default(),
             scene: Handle::default(),
             gltf: Handle::default(),
             llama_tag: LLamaTag,
+            weights: BehaviorWeights::default(),
             visibility: Visibility::Inherited,
             inherited_visibility: InheritedVisibility::HIDDEN,
             view_visibility: ViewVisibility::HIDDEN,
@@ -65,15 +154,102 @@ impl Default for LLamaBundle {
     }
 }
 
+fn move_llamas(
+    mut query: Query<
+        (
+            &Transform,
+            &mut Velocity,
+            &mut LlamaMovement,
+            &LlamaState,
+            &BehaviorWeights,
+        ),
+        With<LLamaTag>,
+    >,
+    tilemap: Res<TileMap>,
+    levels: Res<Assets<LevelAsset>>,
+    level: Query<&Handle<LevelAsset>>,
+    mut rng: ResMut<SimulationRng>,
+) {
+    let Ok(handle) = level.get_single() else {
+        debug!("wtf you doing");
+        return;
+    };
+
+    let Some(level) = levels.get(handle) else {
+        return;
+    };
+
+    let animal_behavior = level.animal_behavior.as_ref().unwrap_or_default();
+    let rng = rng.rng();
+
+    query.iter_mut().for_each(
+        |(transform, mut velocity, mut movement, llama_state, weights)| {
+            let position = transform.translation.truncate();
+
+            // plant the feet for the stomp
+            if !matches!(llama_state, LlamaState::Idle) {
+                velocity.linvel = Vec2::ZERO;
+                return;
+            }
+
+            let target = match &mut *movement {
+                LlamaMovement::Stationary => None,
+                LlamaMovement::Patrol { waypoints, next } => {
+                    let Some(mut waypoint) = waypoints.get(*next).copied() else {
+                        return;
+                    };
+
+                    if position.distance(waypoint) < TILE_SIZE / 2. {
+                        *next = (*next + 1) % waypoints.len();
+                        waypoint = waypoints[*next];
+                    }
+
+                    Some(waypoint)
+                }
+                LlamaMovement::Wander {
+                    center,
+                    radius,
+                    target,
+                } => {
+                    if target.is_some_and(|t| position.distance(t) < TILE_SIZE / 2.) {
+                        *target = None;
+                    }
+
+                    if target.is_none() {
+                        let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+                        let candidate =
+                            *center + Vec2::from_angle(angle) * rng.gen_range(0.0..=*radius);
+
+                        if tilemap.is_free(TileMap::coord(candidate)) {
+                            *target = Some(candidate);
+                        }
+                    }
+
+                    *target
+                }
+            };
+
+            if let Some(target) = target {
+                velocity.linvel = (target - position).normalize_or_zero()
+                    * animal_behavior.llama_speed
+                    * weights.speed;
+            }
+        },
+    );
+}
+
 fn llama_stomp(
     mut cmd: Commands,
-    mut query: Query<(Entity, &Children), (With<LLamaTag>, Without<Cooldown>)>,
+    mut query: Query<
+        (Entity, &Transform, &Children, &mut LlamaState),
+        (With<LLamaTag>, Without<Cooldown>),
+    >,
     telegraphs: Query<With<TelegraphTag>>,
     levels: Res<Assets<LevelAsset>>,
     level: Query<&Handle<LevelAsset>>,
-    positions: Query<&Transform>,
-    sheeps: Query<With<SheepTag>>,
-    rapier_context: Res<RapierContext>,
+    grid: Res<SpatialGrid>,
+    time: Res<Time>,
+    mut rng: ResMut<SimulationRng>,
 ) {
     let Ok(handle) = level.get_single() else {
         debug!("wtf you doing");
@@ -86,65 +262,75 @@ fn llama_stomp(
 
     let animal_behavior = level.animal_behavior.as_ref().unwrap_or_default();
 
-    query.iter_mut().for_each(|(entity, children)| {
-        let Ok(transform) = positions.get(entity) else {
-            return;
-        };
-        let collider = Collider::ball(animal_behavior.llama_stomp_range);
-        let mut sheeps_in_range = Vec::new();
-        rapier_context.intersections_with_shape(
-            transform.translation.truncate(),
-            0.,
-            &collider,
-            QueryFilter::default().predicate(&|e| sheeps.get(e).is_ok()),
-            |e| {
-                let Ok(sheep_transform) = positions.get(e) else {
-                    return true;
-                };
-
-                let direction_to_sheep =
-                    sheep_transform.translation.truncate() - transform.translation.truncate();
-
-                cmd.entity(e)
-                    .insert(Cooldown::new(Duration::from_secs_f32(0.5)))
-                    .insert(ExternalImpulse {
-                        impulse: direction_to_sheep.normalize() * animal_behavior.llama_stomp_force,
-                        ..default()
-                    });
+    query
+        .iter_mut()
+        .for_each(|(entity, transform, children, mut llama_state)| {
+            let position = transform.translation.truncate();
+            let mut sheeps_in_range =
+                grid.query_kind(position, animal_behavior.llama_stomp_range, GridKind::Sheep);
 
-                sheeps_in_range.push(e);
-                true
-            },
-        );
+            match &mut *llama_state {
+                LlamaState::Idle => {
+                    if sheeps_in_range.next().is_some() {
+                        *llama_state = LlamaState::Charging(Timer::from_seconds(
+                            animal_behavior.llama_windup,
+                            TimerMode::Once,
+                        ));
+                    }
+                }
+                LlamaState::Charging(timer) => {
+                    if !timer.tick(time.delta()).finished() {
+                        return;
+                    }
 
-        // *animal_state = AnimalState::Jumping;
-        let tween = Tween::new(
-            EaseFunction::QuadraticOut,
-            Duration::from_millis(100),
-            TransformPositionLens {
-                start: Vec3::ZERO,
-                end: Vec3::new(0., 0., 5.),
-            },
-        )
-        .with_repeat_strategy(RepeatStrategy::MirroredRepeat)
-        .with_repeat_count(RepeatCount::Finite(2));
+                    let tween = Tween::new(
+                        EaseFunction::QuadraticOut,
+                        Duration::from_millis(100),
+                        TransformPositionLens {
+                            start: Vec3::ZERO,
+                            end: Vec3::new(0., 0., 5.),
+                        },
+                    )
+                    .with_repeat_strategy(RepeatStrategy::MirroredRepeat)
+                    .with_repeat_count(RepeatCount::Finite(2));
 
-        cmd.entity(entity).insert(JumpTag);
+                    cmd.entity(entity).insert(JumpTag);
 
-        match children
-            .iter().find(|e| telegraphs.get(**e).is_err())
-        {
-            Some(child) => {
-                cmd.entity(*child).insert(Animator::new(tween));
-            }
-            None => (),
-        };
+                    if let Some(child) = children.iter().find(|e| telegraphs.get(**e).is_err()) {
+                        cmd.entity(*child).insert(Animator::new(tween));
+                    }
 
-        cmd.entity(entity)
-            .insert(Cooldown::new(Duration::from_secs_f32(
-                animal_behavior.llama_stomp_rate + rand::random::<f32>() * 2.,
-            )));
-    });
+                    *llama_state = LlamaState::Stomping(Timer::new(
+                        Duration::from_millis(200),
+                        TimerMode::Once,
+                    ));
+                }
+                LlamaState::Stomping(timer) => {
+                    if !timer.tick(time.delta()).finished() {
+                        return;
+                    }
+
+                    // the impulse lands with the llama, not when it takes off
+                    sheeps_in_range.for_each(|sheep| {
+                        let direction_to_sheep = sheep.position - position;
+
+                        cmd.entity(sheep.entity)
+                            .insert(Cooldown::new(Duration::from_secs_f32(0.5)))
+                            .insert(ExternalImpulse {
+                                impulse: direction_to_sheep.normalize_or_zero()
+                                    * animal_behavior.llama_stomp_force,
+                                ..default()
+                            });
+                    });
+
+                    *llama_state = LlamaState::Idle;
+                    cmd.entity(entity)
+                        .insert(Cooldown::new(Duration::from_secs_f32(
+                            animal_behavior.llama_stomp_rate + rng.rng().gen::<f32>() * 2.,
+                        )));
+                }
+            }
+        });
 }
 
 fn add_telegraph_to_llama(
@@ -168,14 +354,14 @@ fn add_telegraph_to_llama(
 
     query.iter().for_each(|entity| {
         let material = TelegraphMaterial {
-            progress: Vec4::new(0.5, 0., 0., 0.),
+            progress: Vec4::ZERO,
             color: Color::RED,
         };
 
         cmd.entity(entity).with_children(|cmd| {
             cmd.spawn(TelegraphBundle {
                 mesh: meshes.add(Mesh::from(shape::Quad::new(Vec2::splat(
-                    animal_behavior.llama_stomp_range,
+                    animal_behavior.llama_stomp_range * 2.,
                 )))),
                 material: materials.add(material),
                 transform: Transform::from_xyz(0., 0., 0.1),
@@ -187,17 +373,21 @@ fn add_telegraph_to_llama(
 
 fn update_progress(
     telegraphs: Query<(&Handle<TelegraphMaterial>, &Parent)>,
-    cooldown_havers: Query<&Cooldown>,
+    llamas: Query<&LlamaState>,
     mut materials: ResMut<Assets<TelegraphMaterial>>,
 ) {
     telegraphs.iter().for_each(|(material, parent)| {
-        let Ok(cooldown) = cooldown_havers.get(parent.get()) else {
+        let Ok(llama_state) = llamas.get(parent.get()) else {
             return;
         };
         let Some(material) = materials.get_mut(material) else {
             return;
         };
 
-        material.progress.x = cooldown.progress();
+        material.progress.x = match llama_state {
+            LlamaState::Idle => 0.,
+            LlamaState::Charging(timer) => timer.percent(),
+            LlamaState::Stomping(_) => 1.,
+        };
     });
 }
@@ -5,7 +5,9 @@ pub mod dog;
 pub mod llama;
 pub mod physics;
 pub mod sheep;
+pub mod species;
 pub mod telegraph;
+pub mod wolf;
 
 pub struct SheepPlugin;
 impl Plugin for SheepPlugin {
@@ -17,6 +19,8 @@ impl Plugin for SheepPlugin {
             physics::AnimalPhysicsPlugin,
             llama::LlamaPlugin,
             telegraph::TelegraphPlugin,
+            wolf::WolfPlugin,
+            species::SpeciesPlugin,
         ));
     }
 }
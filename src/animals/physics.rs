@@ -1,10 +1,28 @@
 use bevy::prelude::*;
 use bevy_rapier2d::dynamics::Velocity;
 
+use crate::{
+    level::loader::LevelAsset,
+    simulation::SimulationSet,
+    spatial::{GridEntry, GridKind, SpatialGrid},
+};
+
+use super::{
+    dog::{DogGait, DogTag},
+    sheep::{Leader, SheepTag},
+    species::BehaviorWeights,
+    wolf::WolfTag,
+};
+
 pub struct AnimalPhysicsPlugin;
 impl Plugin for AnimalPhysicsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, face_front);
+        app.init_resource::<SpatialGrid>();
+        app.add_systems(
+            FixedUpdate,
+            rebuild_spatial_grid.in_set(SimulationSet::Prepare),
+        );
+        app.add_systems(FixedUpdate, face_front.in_set(SimulationSet::Gameplay));
     }
 }
 
@@ -23,6 +41,43 @@ impl MoveTo {
     }
 }
 
+/// Drains while running and refills while resting. Hitting zero exhausts the
+/// animal until it has recovered back up to the given threshold.
+#[derive(Component)]
+pub struct Stamina {
+    value: f32,
+    exhausted: bool,
+}
+
+impl Default for Stamina {
+    fn default() -> Self {
+        Self {
+            value: 1.,
+            exhausted: false,
+        }
+    }
+}
+
+impl Stamina {
+    pub fn is_exhausted(&self) -> bool {
+        self.exhausted
+    }
+
+    pub fn drain(&mut self, amount: f32) {
+        self.value = (self.value - amount).max(0.);
+        if self.value <= 0. {
+            self.exhausted = true;
+        }
+    }
+
+    pub fn recover(&mut self, amount: f32, recovered_at: f32) {
+        self.value = (self.value + amount).min(1.);
+        if self.exhausted && self.value >= recovered_at {
+            self.exhausted = false;
+        }
+    }
+}
+
 fn face_front(mut query: Query<(&mut Transform, &Velocity)>) {
     query.iter_mut().for_each(|(mut transform, velocity)| {
         let direction = velocity.linvel.normalize_or_zero();
@@ -30,3 +85,59 @@ fn face_front(mut query: Query<(&mut Transform, &Velocity)>) {
         transform.rotation = Quat::from_rotation_z(angle);
     });
 }
+
+fn rebuild_spatial_grid(
+    mut grid: ResMut<SpatialGrid>,
+    sheeps: Query<(Entity, &Transform, &Velocity, Option<&Leader>), With<SheepTag>>,
+    dogs: Query<(Entity, &Transform, &Velocity, &DogGait, &BehaviorWeights), With<DogTag>>,
+    wolves: Query<(Entity, &Transform, &Velocity, &BehaviorWeights), With<WolfTag>>,
+    levels: Res<Assets<LevelAsset>>,
+    level: Query<&Handle<LevelAsset>>,
+) {
+    let animal_behavior = level
+        .get_single()
+        .ok()
+        .and_then(|handle| levels.get(handle))
+        .and_then(|level| level.animal_behavior.as_ref())
+        .unwrap_or_default();
+
+    grid.clear(animal_behavior.vision);
+
+    sheeps
+        .iter()
+        .for_each(|(entity, transform, velocity, leader)| {
+            grid.insert(GridEntry {
+                entity,
+                kind: GridKind::Sheep,
+                position: transform.translation.truncate(),
+                velocity: velocity.linvel,
+                weight: match leader {
+                    Some(_) => animal_behavior.leader_weight,
+                    None => 1.,
+                },
+            });
+        });
+
+    dogs.iter()
+        .for_each(|(entity, transform, velocity, gait, weights)| {
+            grid.insert(GridEntry {
+                entity,
+                kind: GridKind::Dog,
+                position: transform.translation.truncate(),
+                velocity: velocity.linvel,
+                weight: gait.fear(animal_behavior) * weights.fear,
+            });
+        });
+
+    wolves
+        .iter()
+        .for_each(|(entity, transform, velocity, weights)| {
+            grid.insert(GridEntry {
+                entity,
+                kind: GridKind::Predator,
+                position: transform.translation.truncate(),
+                velocity: velocity.linvel,
+                weight: weights.fear,
+            });
+        });
+}
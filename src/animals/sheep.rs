@@ -1,34 +1,158 @@
 use bevy::{gltf::Gltf, prelude::*};
 
 use bevy_rapier2d::prelude::*;
+use rand::Rng;
 
 use crate::{
-    level::loader::LevelAsset,
+    collision,
+    level::{
+        loader::{AnimalBehavior, LevelAsset, Tiles},
+        tilemap::TileMap,
+        TILE_SIZE,
+    },
+    simulation::{HashRng, Interpolated, SimulationRng, SimulationSet, SimulationTick},
+    spatial::{GridKind, SpatialGrid},
     state::{AllowedState, GameState},
     util::Cooldown,
 };
 
-use super::{animations::AnimalState, dog::DogTag, physics::MoveTo };
+use super::{
+    animations::AnimalState,
+    physics::{MoveTo, Stamina},
+    species::BehaviorWeights,
+};
 
 pub struct SheepBehaviorPlugin;
 impl Plugin for SheepBehaviorPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, sheep_flocking);
+        app.add_systems(
+            FixedUpdate,
+            (
+                roll_temperament,
+                update_stamina,
+                compute_flocking,
+                apply_flocking,
+            )
+                .chain()
+                .in_set(SimulationSet::Gameplay),
+        );
     }
 }
 
 #[derive(Component)]
 pub struct SheepTag;
 
+/// Per-sheep multipliers on top of the level wide `AnimalBehavior`.
+#[derive(Component, Clone, Copy)]
+pub struct Temperament {
+    pub fear: f32,
+    pub cohesion: f32,
+    pub speed: f32,
+}
+
+impl Default for Temperament {
+    fn default() -> Self {
+        Self {
+            fear: 1.,
+            cohesion: 1.,
+            speed: 1.,
+        }
+    }
+}
+
+impl Temperament {
+    const BOLD: Self = Self {
+        fear: 0.5,
+        cohesion: 0.8,
+        speed: 1.15,
+    };
+    const TIMID: Self = Self {
+        fear: 1.6,
+        cohesion: 1.4,
+        speed: 1.,
+    };
+    const STUBBORN: Self = Self {
+        fear: 0.7,
+        cohesion: 0.4,
+        speed: 0.8,
+    };
+}
+
+/// The heading of a leader weighs more in the alignment of its neighbours.
+#[derive(Component)]
+pub struct Leader;
+
+/// Idle behaviour of a calm sheep: graze in place and now and then wander
+/// off to a nearby free tile.
+#[derive(Component, Default)]
+pub struct Wander {
+    target: Option<Vec2>,
+    time_left: f32,
+    // rolled once per sheep, keeps the parallel flocking reproducible
+    seed: u64,
+}
+
+impl Wander {
+    // give up on a spot that can't be reached after this many seconds
+    const PATIENCE: f32 = 5.;
+
+    fn update(
+        &mut self,
+        position: Vec2,
+        tilemap: &TileMap,
+        animal_behavior: &AnimalBehavior,
+        tick: u64,
+        delta: f32,
+    ) {
+        let mut rng = HashRng::new(self.seed, tick);
+
+        if let Some(target) = self.target {
+            self.time_left -= delta;
+            if self.time_left <= 0. || position.distance(target) < TILE_SIZE / 2. {
+                self.target = None;
+            }
+            return;
+        }
+
+        if rng.gen::<f32>() >= animal_behavior.wander_chance * delta {
+            return;
+        }
+
+        let range = animal_behavior.wander_range.max(1.) as i32;
+        let coord = TileMap::coord(position)
+            + IVec2::new(rng.gen_range(-range..=range), rng.gen_range(-range..=range));
+
+        if tilemap.is_free(coord) {
+            self.target = Some(TileMap::center(coord));
+            self.time_left = Self::PATIENCE;
+        }
+    }
+
+    fn calm_down(&mut self) {
+        self.target = None;
+    }
+}
+
+/// Steering result of the flocking compute phase, applied to the velocity afterwards.
+#[derive(Component, Default)]
+pub struct DesiredVelocity(pub Vec2);
+
 #[derive(Bundle)]
 pub struct SheepBundle {
     pub body: RigidBody,
     pub collider: Collider,
+    pub collision_groups: CollisionGroups,
+    pub interpolated: Interpolated,
     pub velocity: Velocity,
+    pub desired_velocity: DesiredVelocity,
+    pub stamina: Stamina,
+    pub temperament: Temperament,
+    pub wander: Wander,
     pub scene: Handle<Scene>,
     pub gltf: Handle<Gltf>,
     pub state: AnimalState,
     pub sheep_tag: SheepTag,
+    pub weights: BehaviorWeights,
     pub visibility: Visibility,
     pub inherited_visibility: InheritedVisibility,
     pub view_visibility: ViewVisibility,
@@ -45,12 +169,19 @@ impl Default for SheepBundle {
         Self {
             body: RigidBody::Dynamic,
             collider: Collider::ball(2.),
+            collision_groups: collision::sheep(),
+            interpolated: Interpolated::default(),
             velocity: Velocity::default(),
+            desired_velocity: DesiredVelocity::default(),
+            stamina: Stamina::default(),
+            temperament: Temperament::default(),
+            wander: Wander::default(),
             scene: Handle::default(),
             gltf: Handle::default(),
             state: AnimalState::Idle,
             name: Name::new("sheep"),
             sheep_tag: SheepTag,
+            weights: BehaviorWeights::default(),
             impuls: ExternalImpulse::default(),
             visibility: Visibility::Inherited,
             inherited_visibility: InheritedVisibility::HIDDEN,
@@ -62,15 +193,26 @@ impl Default for SheepBundle {
         }
     }
 }
-fn sheep_flocking(
-    mut velocities: Query<&mut Velocity>,
-    dogs: Query<Entity, With<DogTag>>,
-    sheeps: Query<Entity, (With<SheepTag>, Without<Cooldown>)>,
-    move_to: Query<&MoveTo>,
-    positions: Query<&Transform>,
-    rapier_context: Res<RapierContext>,
+fn compute_flocking(
+    mut sheeps: Query<
+        (
+            Entity,
+            &Transform,
+            &Velocity,
+            &Stamina,
+            &Temperament,
+            Option<&MoveTo>,
+            &mut Wander,
+            &mut DesiredVelocity,
+        ),
+        (With<SheepTag>, Without<Cooldown>),
+    >,
+    grid: Res<SpatialGrid>,
+    tilemap: Res<TileMap>,
     levels: Res<Assets<LevelAsset>>,
     level: Query<&Handle<LevelAsset>>,
+    time: Res<Time>,
+    tick: Res<SimulationTick>,
 ) {
     let Ok(handle) = level.get_single() else {
         debug!("wtf you doing");
@@ -82,85 +224,200 @@ fn sheep_flocking(
     };
 
     let animal_behavior = level.animal_behavior.as_ref().unwrap_or_default();
+    let delta = time.delta_seconds();
 
-    sheeps.iter().for_each(|entity| {
-        let transform = positions.get(entity).unwrap();
-        let collider = Collider::ball(animal_behavior.vision);
-        let mut sheeps_in_range = Vec::new();
-
-        rapier_context.intersections_with_shape(
-            transform.translation.truncate(),
-            Rot::default(),
-            &collider,
-            QueryFilter::default().predicate(&|ent| ent != entity && sheeps.get(ent).is_ok()),
-            |ent| {
-                sheeps_in_range.push(ent);
-                true
-            },
-        );
+    sheeps.par_iter_mut().for_each(
+        |(entity, transform, velocity, stamina, temperament, move_to, mut wander, mut desired)| {
+            let position = transform.translation.truncate();
+
+            let mut neighbours = 0;
+            let mut position_sum = Vec2::ZERO;
+            let mut alignment_sum = Vec2::ZERO;
+            let mut alignment_weight = 0.;
+            let mut separation_sum = Vec2::ZERO;
+
+            grid.query_kind(position, animal_behavior.vision, GridKind::Sheep)
+                .filter(|other| other.entity != entity)
+                .for_each(|other| {
+                    neighbours += 1;
+                    position_sum += other.position;
+                    alignment_sum += other.velocity.normalize_or_zero() * other.weight;
+                    alignment_weight += other.weight;
+                    separation_sum += (position - other.position).normalize_or_zero();
+                });
+
+            let mut acc_direction = Vec2::ZERO;
+
+            if neighbours > 0 {
+                let count = neighbours as f32;
+
+                let cohesion = (position_sum / count - position).normalize_or_zero();
+                acc_direction += cohesion * animal_behavior.cohesion * temperament.cohesion;
 
-        let mut acc_direction = Vec2::ZERO;
-
-        if !sheeps_in_range.is_empty() {
-            let average_position = sheeps_in_range
-                .iter()
-                .map(|ent| positions.get(*ent).unwrap().translation.truncate())
-                .sum::<Vec2>()
-                / sheeps_in_range.len() as f32;
-
-            let cohesion =
-                (average_position - transform.translation.truncate()).normalize_or_zero();
-            acc_direction += cohesion * animal_behavior.cohesion;
-
-            let alignment = sheeps_in_range
-                .iter()
-                .map(|ent| velocities.get(*ent).unwrap().linvel.normalize_or_zero())
-                .sum::<Vec2>()
-                / sheeps_in_range.len() as f32;
-            acc_direction += alignment * animal_behavior.alignment;
-
-            let separation = (sheeps_in_range
-                .iter()
-                .map(|ent| {
-                    let position = positions.get(*ent).unwrap().translation.truncate();
-                    let distance = position.distance(transform.translation.truncate());
-                    let direction = transform.translation.truncate() - position;
-                    direction / distance
+                let alignment = alignment_sum / alignment_weight.max(f32::EPSILON);
+                acc_direction += alignment * animal_behavior.alignment;
+
+                let separation = (separation_sum / count).normalize_or_zero();
+                acc_direction += separation * animal_behavior.separation;
+            }
+
+            // sheep flee from wolves just like from the dog
+            let mut threats_in_range = 0;
+            let mut pressure = 0.;
+            let flee_sum = grid
+                .query(position, animal_behavior.vision * 4.)
+                .filter(|other| matches!(other.kind, GridKind::Dog | GridKind::Predator))
+                .map(|threat| {
+                    threats_in_range += 1;
+                    pressure += threat.weight;
+                    (position - threat.position).normalize_or_zero()
                 })
-                .sum::<Vec2>()
-                / sheeps_in_range.len() as f32)
-                .normalize_or_zero();
-            acc_direction += separation * animal_behavior.separation;
-        }
+                .sum::<Vec2>();
+
+            // exhausted sheep are too tired to care about the dog
+            let flee = if threats_in_range > 0 && !stamina.is_exhausted() {
+                flee_sum / threats_in_range as f32
+            } else {
+                Vec2::ZERO
+            };
+
+            // a crouching dog pushes less than one running at the flock
+            let pressure = pressure / threats_in_range.max(1) as f32;
 
-        let flee = dogs
-            .iter()
-            .filter_map(|ent| {
-                let position = positions.get(ent).unwrap().translation.truncate();
-                let distance = position.distance(transform.translation.truncate());
+            let max_speed = match stamina.is_exhausted() {
+                true => animal_behavior.exhausted_speed,
+                false => animal_behavior.sheep_speed,
+            } * temperament.speed;
 
-                if distance > animal_behavior.vision * 4. {
-                    return None;
+            acc_direction +=
+                flee.normalize_or_zero() * animal_behavior.fear * temperament.fear * pressure;
+
+            // look ahead along the current heading, so sheep turn before they hit something
+            let range = animal_behavior.avoidance_range;
+            let lookahead = position + velocity.linvel.normalize_or_zero() * range;
+
+            let obstacles = tilemap.repulsion(lookahead, range, |tile| tile == Tiles::Wall);
+            acc_direction += obstacles * animal_behavior.obstacle_avoidance;
+
+            let hazards = tilemap.repulsion(lookahead, range, |tile| tile == Tiles::Trap);
+            acc_direction += hazards * animal_behavior.hazard_avoidance;
+
+            acc_direction += velocity.linvel.normalize_or_zero();
+
+            if let Some(move_to) = move_to {
+                let direction = (move_to.postion() - position).normalize_or_zero();
+                acc_direction += direction * animal_behavior.motivation;
+            }
+
+            let speed = match threats_in_range {
+                // no dog around, slow down to graze or drift to a nearby spot
+                0 => {
+                    wander.update(position, &tilemap, animal_behavior, tick.0, delta);
+                    match wander.target {
+                        Some(target) => {
+                            acc_direction += (target - position).normalize_or_zero();
+                            animal_behavior.wander_speed.min(max_speed)
+                        }
+                        None => {
+                            velocity.linvel.length()
+                                * (1. - animal_behavior.graze_slowdown * delta).max(0.)
+                        }
+                    }
                 }
+                _ => {
+                    wander.calm_down();
+                    (velocity.linvel.length() + flee.length() * pressure).min(max_speed)
+                }
+            };
+
+            desired.0 = acc_direction.normalize_or_zero() * speed;
+        },
+    );
+}
+
+pub fn roll_temperament(
+    mut cmd: Commands,
+    mut sheeps: Query<(Entity, &BehaviorWeights, &mut Temperament, &mut Wander), Added<SheepTag>>,
+    levels: Res<Assets<LevelAsset>>,
+    level: Query<&Handle<LevelAsset>>,
+    mut rng: ResMut<SimulationRng>,
+) {
+    let Ok(handle) = level.get_single() else {
+        return;
+    };
+
+    let Some(level) = levels.get(handle) else {
+        return;
+    };
+
+    let animal_behavior = level.animal_behavior.as_ref().unwrap_or_default();
+    let rng = rng.rng();
 
-                let direction = transform.translation.truncate() - position;
-                Some(direction / distance)
-            })
-            .sum::<Vec2>()
-            / dogs.iter().len() as f32;
+    sheeps
+        .iter_mut()
+        .for_each(|(entity, weights, mut temperament, mut wander)| {
+            wander.seed = rng.gen();
 
-        acc_direction += flee.normalize_or_zero() * animal_behavior.fear;
+            let roll = rng.gen::<f32>();
+            let bold = animal_behavior.bold_chance;
+            let timid = bold + animal_behavior.timid_chance;
+            let stubborn = timid + animal_behavior.stubborn_chance;
 
-        let mut velocity = velocities.get_mut(entity).unwrap();
-        acc_direction += velocity.linvel.normalize_or_zero();
+            let base = match roll {
+                r if r < bold => Temperament::BOLD,
+                r if r < timid => Temperament::TIMID,
+                r if r < stubborn => Temperament::STUBBORN,
+                _ => Temperament::default(),
+            };
 
-        if let Ok(move_to) = move_to.get(entity) {
-            let direction =
-                (move_to.postion() - transform.translation.truncate()).normalize_or_zero();
-            acc_direction += direction * animal_behavior.motivation;
+            let spread = animal_behavior.temperament_spread;
+            let mut vary = |value: f32| (value * (1. + rng.gen_range(-1.0..=1.0) * spread)).max(0.);
+
+            *temperament = Temperament {
+                fear: vary(base.fear) * weights.fear,
+                cohesion: vary(base.cohesion) * weights.cohesion,
+                speed: vary(base.speed) * weights.speed,
+            };
+
+            if rng.gen::<f32>() < animal_behavior.leader_chance {
+                cmd.entity(entity).insert(Leader);
+            }
+        });
+}
+
+fn update_stamina(
+    mut sheeps: Query<(&AnimalState, &mut Stamina), With<SheepTag>>,
+    levels: Res<Assets<LevelAsset>>,
+    level: Query<&Handle<LevelAsset>>,
+    time: Res<Time>,
+) {
+    let Ok(handle) = level.get_single() else {
+        return;
+    };
+
+    let Some(level) = levels.get(handle) else {
+        return;
+    };
+
+    let animal_behavior = level.animal_behavior.as_ref().unwrap_or_default();
+    let delta = time.delta_seconds();
+
+    sheeps.iter_mut().for_each(|(state, mut stamina)| {
+        let regen = animal_behavior.stamina_regen * delta;
+
+        match state {
+            AnimalState::Running => stamina.drain(animal_behavior.stamina_drain * delta),
+            AnimalState::Idle => stamina.recover(regen, animal_behavior.stamina_recovery),
+            AnimalState::Walking => stamina.recover(regen / 2., animal_behavior.stamina_recovery),
+            _ => (),
         }
+    });
+}
 
-        velocity.linvel = acc_direction.normalize_or_zero()
-            * (velocity.linvel.length() + flee.length()).min(animal_behavior.sheep_speed);
+fn apply_flocking(
+    mut sheeps: Query<(&DesiredVelocity, &mut Velocity), (With<SheepTag>, Without<Cooldown>)>,
+) {
+    sheeps.par_iter_mut().for_each(|(desired, mut velocity)| {
+        velocity.linvel = desired.0;
     });
 }
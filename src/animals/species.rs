@@ -0,0 +1,150 @@
+use bevy::{
+    asset::{AssetLoader, AsyncReadExt},
+    gltf::Gltf,
+    prelude::*,
+    utils::HashMap,
+};
+use bevy_rapier2d::{
+    dynamics::Damping,
+    geometry::{Collider, ColliderMassProperties},
+};
+use serde::Deserialize;
+
+use super::animations::AnimationNames;
+use crate::util::{restyle_new_materials, NewMaterials};
+
+pub struct SpeciesPlugin;
+impl Plugin for SpeciesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<SpeciesAsset>();
+        app.register_asset_loader(SpeciesAssetLoader);
+        app.add_systems(Update, tint_models);
+    }
+}
+
+pub struct SpeciesAssetLoader;
+impl AssetLoader for SpeciesAssetLoader {
+    type Asset = SpeciesAsset;
+    type Settings = ();
+    type Error = anyhow::Error;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut bevy::asset::io::Reader,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut bevy::asset::LoadContext,
+    ) -> bevy::utils::BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            ron::de::from_bytes(&bytes).map_err(|e| anyhow::anyhow!("{}", e))
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["species.ron"]
+    }
+}
+
+/// Everything that makes an animal look and move the way it does. Levels pick
+/// a species for each role by its `name`.
+#[derive(Asset, TypePath, Debug, Deserialize)]
+pub struct SpeciesAsset {
+    pub name: String,
+    pub model: String,
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+    #[serde(default)]
+    pub animations: AnimationNames,
+    pub collider_radius: f32,
+    pub mass: f32,
+    #[serde(default)]
+    pub linear_damping: f32,
+    #[serde(default)]
+    pub angular_damping: f32,
+    #[serde(default)]
+    pub behavior: BehaviorWeights,
+    /// rgb multiplied onto the model's materials, to tell apart species
+    /// sharing a model
+    #[serde(default)]
+    pub tint: Option<(f32, f32, f32)>,
+}
+
+fn default_scale() -> f32 {
+    1.
+}
+
+impl SpeciesAsset {
+    /// Overrides the model and body of a freshly spawned animal bundle.
+    pub fn components(&self, server: &AssetServer) -> impl Bundle {
+        (
+            server.load::<Scene>(format!("{}#Scene0", self.model)),
+            server.load::<Gltf>(self.model.clone()),
+            Collider::ball(self.collider_radius),
+            ColliderMassProperties::Mass(self.mass),
+            Damping {
+                linear_damping: self.linear_damping,
+                angular_damping: self.angular_damping,
+            },
+            self.animations.clone(),
+            self.behavior,
+            Tint(
+                self.tint
+                    .map_or(Color::WHITE, |(r, g, b)| Color::rgb(r, g, b)),
+            ),
+        )
+    }
+
+    pub fn transform(&self, position: Vec2) -> Transform {
+        Transform::from_translation(position.extend(0.)).with_scale(Vec3::splat(self.scale))
+    }
+}
+
+/// Color the model of an animal is multiplied with.
+#[derive(Component, Clone, Copy)]
+pub struct Tint(pub Color);
+
+fn tint_models(
+    mut handles: NewMaterials,
+    parents: Query<&Parent>,
+    tints: Query<&Tint>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut tinted: Local<HashMap<(AssetId<StandardMaterial>, u32), Handle<StandardMaterial>>>,
+) {
+    restyle_new_materials(
+        &mut handles,
+        &parents,
+        &tints,
+        &mut materials,
+        &mut tinted,
+        |tint| (tint.0 != Color::WHITE).then(|| tint.0.as_rgba_u32()),
+        |tint, material| material.base_color = material.base_color * Vec4::from(tint.0),
+    );
+}
+
+pub fn find<'a>(species: &'a Assets<SpeciesAsset>, name: &str) -> Option<&'a SpeciesAsset> {
+    species
+        .iter()
+        .map(|(_, species)| species)
+        .find(|species| species.name == name)
+}
+
+/// Multipliers on top of the level's `AnimalBehavior`. For the flock `fear`
+/// is how easily it gets spooked, for dogs and predators how scary they are.
+#[derive(Component, Deserialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct BehaviorWeights {
+    pub speed: f32,
+    pub fear: f32,
+    pub cohesion: f32,
+}
+
+impl Default for BehaviorWeights {
+    fn default() -> Self {
+        Self {
+            speed: 1.,
+            fear: 1.,
+            cohesion: 1.,
+        }
+    }
+}
@@ -0,0 +1,234 @@
+use std::time::Duration;
+
+use bevy::{
+    audio::{PlaybackMode, Volume, VolumeLevel},
+    gltf::Gltf,
+    prelude::*,
+    utils::HashSet,
+};
+use bevy_rapier2d::{
+    dynamics::{Damping, RigidBody, Velocity},
+    geometry::{Collider, ColliderMassProperties, CollisionGroups},
+};
+
+use super::{animations::AnimalState, species::BehaviorWeights};
+use crate::{
+    collision,
+    death::kill,
+    level::{loader::LevelAsset, LossCause, Score},
+    simulation::{Interpolated, SimulationSet},
+    spatial::{GridKind, SpatialGrid},
+    state::{AllowedState, GameState},
+    trap::DeathSound,
+    util::Cooldown,
+    GameSettings,
+};
+
+pub struct WolfPlugin;
+impl Plugin for WolfPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            wolf_hunt
+                .run_if(in_state(GameState::Game))
+                .in_set(SimulationSet::Gameplay),
+        );
+    }
+}
+
+#[derive(Component)]
+pub struct WolfTag;
+
+#[derive(Component, Default)]
+pub enum WolfState {
+    #[default]
+    Hunting,
+    Fleeing(f32),
+}
+
+#[derive(Bundle)]
+pub struct WolfBundle {
+    pub body: RigidBody,
+    pub collider: Collider,
+    pub collision_groups: CollisionGroups,
+    pub interpolated: Interpolated,
+    pub scene: Handle<Scene>,
+    pub gltf: Handle<Gltf>,
+    pub wolf_tag: WolfTag,
+    pub weights: BehaviorWeights,
+    pub wolf_state: WolfState,
+    pub state: AnimalState,
+    pub velocity: Velocity,
+    pub visibility: Visibility,
+    pub inherited_visibility: InheritedVisibility,
+    pub view_visibility: ViewVisibility,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+    pub name: Name,
+    pub damping: Damping,
+    pub mass: ColliderMassProperties,
+    pub allowed_game_states: AllowedState,
+}
+
+impl Default for WolfBundle {
+    fn default() -> Self {
+        Self {
+            body: RigidBody::Dynamic,
+            collider: Collider::ball(2.),
+            collision_groups: collision::predator(),
+            interpolated: Interpolated::default(),
+            velocity: Velocity::default(),
+            state: AnimalState::Idle,
+            scene: Handle::default(),
+            gltf: Handle::default(),
+            wolf_tag: WolfTag,
+            weights: BehaviorWeights::default(),
+            wolf_state: WolfState::default(),
+            visibility: Visibility::Inherited,
+            inherited_visibility: InheritedVisibility::HIDDEN,
+            view_visibility: ViewVisibility::HIDDEN,
+            transform: Transform::IDENTITY,
+            global_transform: GlobalTransform::IDENTITY,
+            name: Name::new("wolf"),
+            damping: Damping {
+                linear_damping: 3.,
+                angular_damping: 1.,
+            },
+            allowed_game_states: AllowedState::new(GameState::Game),
+            mass: ColliderMassProperties::Mass(15.),
+        }
+    }
+}
+
+fn wolf_hunt(
+    mut cmd: Commands,
+    mut wolves: Query<
+        (
+            Entity,
+            &Transform,
+            &mut Velocity,
+            &mut WolfState,
+            &BehaviorWeights,
+            Option<&Cooldown>,
+        ),
+        With<WolfTag>,
+    >,
+    grid: Res<SpatialGrid>,
+    mut score: ResMut<Score>,
+    levels: Res<Assets<LevelAsset>>,
+    level: Query<&Handle<LevelAsset>>,
+    time: Res<Time>,
+    server: Res<AssetServer>,
+    death_sound: Query<With<DeathSound>>,
+    volume: Res<GameSettings>,
+) {
+    let Ok(handle) = level.get_single() else {
+        debug!("wtf you doing");
+        return;
+    };
+
+    let Some(level) = levels.get(handle) else {
+        return;
+    };
+
+    let animal_behavior = level.animal_behavior.as_ref().unwrap_or_default();
+    let mut eaten_sheeps = HashSet::new();
+
+    wolves.iter_mut().for_each(
+        |(entity, transform, mut velocity, mut wolf_state, weights, eating)| {
+            let position = transform.translation.truncate();
+
+            // the dog got too close, run for it
+            if grid
+                .query_kind(position, animal_behavior.wolf_scare_radius, GridKind::Dog)
+                .next()
+                .is_some()
+            {
+                *wolf_state = WolfState::Fleeing(animal_behavior.wolf_flee_time);
+            }
+
+            if let WolfState::Fleeing(time_left) = &mut *wolf_state {
+                *time_left -= time.delta_seconds();
+
+                let away = grid
+                    .query_kind(
+                        position,
+                        animal_behavior.wolf_scare_radius * 4.,
+                        GridKind::Dog,
+                    )
+                    .map(|dog| (position - dog.position).normalize_or_zero())
+                    .sum::<Vec2>();
+
+                if away != Vec2::ZERO {
+                    velocity.linvel =
+                        away.normalize_or_zero() * animal_behavior.wolf_speed * weights.speed;
+                }
+
+                if *time_left <= 0. {
+                    *wolf_state = WolfState::Hunting;
+                }
+                return;
+            }
+
+            // still busy with the last one
+            if eating.is_some() {
+                velocity.linvel = Vec2::ZERO;
+                return;
+            }
+
+            // stragglers with the fewest neighbours first, the closest of those
+            let Some((prey, _, distance)) = grid
+                .query_kind(position, animal_behavior.wolf_vision, GridKind::Sheep)
+                .filter(|sheep| !eaten_sheeps.contains(&sheep.entity))
+                .map(|sheep| {
+                    let neighbours = grid
+                        .query_kind(sheep.position, animal_behavior.vision, GridKind::Sheep)
+                        .count();
+                    (sheep, neighbours, sheep.position.distance(position))
+                })
+                .min_by(
+                    |(_, a_neighbours, a_distance), (_, b_neighbours, b_distance)| {
+                        (a_neighbours, a_distance)
+                            .partial_cmp(&(b_neighbours, b_distance))
+                            .unwrap_or(std::cmp::Ordering::Equal)
+                    },
+                )
+            else {
+                return;
+            };
+
+            if distance > animal_behavior.wolf_kill_range {
+                velocity.linvel = (prey.position - position).normalize_or_zero()
+                    * animal_behavior.wolf_speed
+                    * weights.speed;
+                return;
+            }
+
+            eaten_sheeps.insert(prey.entity);
+            velocity.linvel = Vec2::ZERO;
+
+            cmd.entity(entity)
+                .insert(Cooldown::new(Duration::from_secs_f32(
+                    animal_behavior.wolf_kill_rate,
+                )));
+
+            kill(&mut cmd, prey.entity, LossCause::Wolf);
+
+            if death_sound.iter().count() > 2 {
+                return;
+            }
+
+            cmd.spawn(AudioBundle {
+                source: server.load("audio/sheep_death.ogg"),
+                settings: PlaybackSettings {
+                    mode: PlaybackMode::Despawn,
+                    volume: Volume::Absolute(VolumeLevel::new(volume.effects * 0.5)),
+                    ..default()
+                },
+            })
+            .insert(DeathSound);
+        },
+    );
+
+    score.record_loss(LossCause::Wolf, eaten_sheeps.len());
+}
@@ -2,13 +2,16 @@ use bevy::core_pipeline::bloom::{BloomCompositeMode, BloomPrefilterSettings, Blo
 use bevy::input::mouse::MouseWheel;
 use bevy::prelude::*;
 
-use crate::animals::dog::DogTag;
+use crate::{animals::dog::DogTag, level::builder::LoadLevelEvent, selection::Selected};
+
+const DEFAULT_ZOOM: f32 = 100.;
 
 pub struct CameraPlugin;
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, spawn_camera);
         app.add_systems(Update, (zoom, follow_camera));
+        app.add_systems(Update, reset_zoom.run_if(on_event::<LoadLevelEvent>()));
     }
 }
 
@@ -34,7 +37,7 @@ fn spawn_camera(mut cmd: Commands) {
         },
         ..default()
     })
-    .insert(ZoomDistance(100.))
+    .insert(ZoomDistance(DEFAULT_ZOOM))
     .insert(BloomSettings {
         intensity: 0.3,
         composite_mode: BloomCompositeMode::Additive,
@@ -62,21 +65,33 @@ fn zoom(mut query: Query<&mut ZoomDistance>, mut wheel_events: EventReader<Mouse
     })
 }
 
+fn reset_zoom(mut query: Query<&mut ZoomDistance>) {
+    query.iter_mut().for_each(|mut zoom| zoom.0 = DEFAULT_ZOOM);
+}
+
 fn follow_camera(
     mut camera: Query<(Entity, &ZoomDistance), With<Camera>>,
     mut postions: Query<&mut Transform>,
-    dogs: Query<Entity, With<DogTag>>,
+    dogs: Query<(Entity, Has<Selected>), With<DogTag>>,
 ) {
     let Ok((camera, zoom)) = camera.get_single_mut() else {
         return;
     };
 
-    let avarage_dog_position = dogs.iter().fold(Vec3::ZERO, |acc, dog| {
-        let Ok(transform) = postions.get_mut(dog) else {
+    // keep the selected dogs in view, all of them if none is selected
+    let any_selected = dogs.iter().any(|(_, selected)| selected);
+    let followed = dogs
+        .iter()
+        .filter(|(_, selected)| *selected || !any_selected)
+        .map(|(dog, _)| dog)
+        .collect::<Vec<_>>();
+
+    let avarage_dog_position = followed.iter().fold(Vec3::ZERO, |acc, dog| {
+        let Ok(transform) = postions.get_mut(*dog) else {
             return acc;
         };
         acc + transform.translation
-    }) / dogs.iter().len() as f32;
+    }) / followed.len() as f32;
 
     let Ok(mut cam_trans) = postions.get_mut(camera) else {
         return;
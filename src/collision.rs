@@ -0,0 +1,32 @@
+use bevy_rapier2d::geometry::{CollisionGroups, Group};
+
+// Every collider in the game is a member of exactly one of these layers.
+pub const SHEEP: Group = Group::GROUP_1;
+pub const DOG: Group = Group::GROUP_2;
+/// Anything that threatens the flock, wolves as well as llamas.
+pub const PREDATOR: Group = Group::GROUP_3;
+pub const WALL: Group = Group::GROUP_4;
+/// Keeps animals in, the dog can hop over.
+pub const FENCE: Group = Group::GROUP_5;
+/// Goals and traps, they only ever care about sheep.
+pub const SENSOR: Group = Group::GROUP_6;
+
+pub fn sheep() -> CollisionGroups {
+    CollisionGroups::new(SHEEP, SHEEP | DOG | PREDATOR | WALL | FENCE | SENSOR)
+}
+
+pub fn dog() -> CollisionGroups {
+    CollisionGroups::new(DOG, SHEEP | DOG | PREDATOR | WALL)
+}
+
+pub fn predator() -> CollisionGroups {
+    CollisionGroups::new(PREDATOR, SHEEP | DOG | PREDATOR | WALL | FENCE)
+}
+
+pub fn wall() -> CollisionGroups {
+    CollisionGroups::new(WALL, SHEEP | DOG | PREDATOR)
+}
+
+pub fn sensor() -> CollisionGroups {
+    CollisionGroups::new(SENSOR, SHEEP)
+}
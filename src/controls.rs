@@ -1,14 +1,18 @@
 use bevy::{
     audio::{PlaybackMode, Volume, VolumeLevel},
-    input::{mouse::MouseButtonInput, touch::TouchPhase},
+    input::{mouse::MouseButtonInput, touch::TouchPhase, ButtonState},
     prelude::*,
 };
-use bevy_rapier2d::dynamics::Velocity;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    animals::{dog::DogTag, physics::MoveTo},
+    animals::{
+        dog::{BarkEvent, DogGait, DogTag, Steering, Waypoints},
+        physics::MoveTo,
+    },
     camera::MainCamera,
-    level::loader::LevelAsset,
+    selection::{DogIndex, Selected},
+    simulation::{SimulationSet, SimulationState},
     GameSettings,
 };
 
@@ -16,42 +20,124 @@ pub struct ControlPlugin;
 impl Plugin for ControlPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<MapClickEvent>();
+        app.init_resource::<DogInput>();
         app.add_systems(Update, touch_event.run_if(on_event::<TouchInput>()));
         app.add_systems(Update, click_event.run_if(on_event::<MouseButtonInput>()));
-        app.add_systems(Update, command_dog.run_if(on_event::<MapClickEvent>()));
-        app.add_systems(Update, keyboard_control);
+        app.add_systems(
+            Update,
+            (
+                command_dog.run_if(on_event::<MapClickEvent>()),
+                (gait_control, keyboard_control, bark_control).chain(),
+            )
+                .run_if(in_state(SimulationState::Running)),
+        );
+        app.add_systems(FixedUpdate, apply_dog_input.in_set(SimulationSet::Input));
     }
 }
 
+/// Everything the player can tell the dogs, addressed by `DogIndex` so it
+/// means the same thing in a replay.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum DogCommand {
+    Move {
+        dogs: Vec<usize>,
+        target: (f32, f32),
+        queued: bool,
+    },
+    Steer {
+        dogs: Vec<usize>,
+        direction: (f32, f32),
+    },
+    Gait {
+        dogs: Vec<usize>,
+        gait: DogGait,
+    },
+    Bark {
+        dogs: Vec<usize>,
+    },
+}
+
+/// Commands waiting for the next fixed tick.
+#[derive(Resource, Default)]
+pub struct DogInput {
+    pending: Vec<DogCommand>,
+}
+
+impl DogInput {
+    pub fn push(&mut self, command: DogCommand) {
+        // frames outnumber ticks, held keys would repeat until the tick applies them
+        if self.pending.last() != Some(&command) {
+            self.pending.push(command);
+        }
+    }
+
+    pub fn pending(&self) -> &[DogCommand] {
+        &self.pending
+    }
+
+    pub fn clear(&mut self) {
+        self.pending.clear();
+    }
+}
+
+fn selected_indices<'a>(dogs: impl Iterator<Item = &'a DogIndex>) -> Vec<usize> {
+    let mut indices: Vec<usize> = dogs.map(|index| index.0).collect();
+    indices.sort_unstable();
+    indices
+}
+
 #[derive(Event)]
 pub struct MapClickEvent {
     translation: Vec3,
+    screen_position: Vec2,
     button: MouseButton,
+    state: ButtonState,
+    queued: bool,
+    from_player: bool,
 }
 impl MapClickEvent {
+    /// A right click on the map, the way orders come from code instead of the mouse.
+    pub fn order(translation: Vec3) -> Self {
+        Self {
+            translation,
+            screen_position: Vec2::ZERO,
+            button: MouseButton::Right,
+            state: ButtonState::Pressed,
+            queued: false,
+            from_player: false,
+        }
+    }
     pub fn translation(&self) -> Vec3 {
         self.translation
     }
+    pub fn screen_position(&self) -> Vec2 {
+        self.screen_position
+    }
     pub fn button(&self) -> MouseButton {
         self.button
     }
+    pub fn pressed(&self) -> bool {
+        self.state == ButtonState::Pressed
+    }
+    pub fn released(&self) -> bool {
+        self.state == ButtonState::Released
+    }
+    /// Shift was held, orders get appended instead of replacing the current one.
+    pub fn queued(&self) -> bool {
+        self.queued
+    }
+    /// Clicked or tapped, not an `order` from code.
+    pub fn from_player(&self) -> bool {
+        self.from_player
+    }
 }
 
 fn keyboard_control(
     inputs: Res<Input<KeyCode>>,
-    mut dogs: Query<&mut Velocity, With<DogTag>>,
-    levels: Res<Assets<LevelAsset>>,
-    level: Query<&Handle<LevelAsset>>,
+    dogs: Query<&DogIndex, (With<DogTag>, With<Selected>)>,
+    mut input: ResMut<DogInput>,
+    mut last: Local<(Vec2, Vec<usize>)>,
 ) {
-    let Ok(handle) = level.get_single() else {
-        debug!("wtf you doing");
-        return;
-    };
-
-    let Some(level) = levels.get(handle) else {
-        return;
-    };
-
     let mut direction = Vec2::ZERO;
     if inputs.pressed(KeyCode::Up) || inputs.pressed(KeyCode::W) {
         direction.y -= 1.;
@@ -73,13 +159,66 @@ fn keyboard_control(
     }
 
 
-    if direction == Vec2::ZERO {
+    let direction = direction.normalize_or_zero();
+    let selected = selected_indices(dogs.iter());
+
+    let (last_direction, last_selected) = &*last;
+    if direction == *last_direction && selected == *last_selected {
         return;
     }
 
-    dogs.iter_mut().for_each(|mut velocity| {
-        velocity.linvel = direction.normalize_or_zero()
-            * level.animal_behavior.as_ref().unwrap_or_default().dog_speed;
+    // dogs that dropped out of the selection stop where they are
+    if *last_direction != Vec2::ZERO && selected != *last_selected {
+        input.push(DogCommand::Steer {
+            dogs: last_selected.clone(),
+            direction: (0., 0.),
+        });
+    }
+
+    input.push(DogCommand::Steer {
+        dogs: selected.clone(),
+        direction: direction.into(),
+    });
+    *last = (direction, selected);
+}
+
+fn gait_control(
+    inputs: Res<Input<KeyCode>>,
+    dogs: Query<(&DogIndex, &DogGait), (With<DogTag>, With<Selected>)>,
+    mut input: ResMut<DogInput>,
+) {
+    // the selection follows the dog with the lowest index
+    let Some((_, gait)) = dogs.iter().min_by_key(|(index, _)| index.0) else {
+        return;
+    };
+
+    let next = match *gait {
+        _ if inputs.pressed(KeyCode::E) => DogGait::Sprint,
+        DogGait::Sprint => DogGait::Normal,
+        DogGait::Crouch if inputs.just_pressed(KeyCode::C) => DogGait::Normal,
+        _ if inputs.just_pressed(KeyCode::C) => DogGait::Crouch,
+        current => current,
+    };
+
+    if dogs.iter().any(|(_, gait)| *gait != next) {
+        input.push(DogCommand::Gait {
+            dogs: selected_indices(dogs.iter().map(|(index, _)| index)),
+            gait: next,
+        });
+    }
+}
+
+fn bark_control(
+    inputs: Res<Input<KeyCode>>,
+    dogs: Query<&DogIndex, (With<DogTag>, With<Selected>)>,
+    mut input: ResMut<DogInput>,
+) {
+    if !inputs.just_pressed(KeyCode::Space) {
+        return;
+    }
+
+    input.push(DogCommand::Bark {
+        dogs: selected_indices(dogs.iter()),
     });
 }
 
@@ -104,7 +243,11 @@ fn touch_event(
         if let Some(pos) = intersect_ray_with_z_zero(ray.origin, ray.direction) {
             events.send(MapClickEvent {
                 translation: pos,
+                screen_position: event.position,
                 button: MouseButton::Right,
+                state: ButtonState::Pressed,
+                queued: false,
+                from_player: true,
             });
         }
     });
@@ -115,11 +258,14 @@ fn click_event(
     mut events: EventWriter<MapClickEvent>,
     camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
     windows: Query<&Window>,
+    inputs: Res<Input<KeyCode>>,
 ) {
     let Ok((cam, cam_trans)) = camera.get_single() else {
         return;
     };
 
+    let queued = inputs.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+
     mouse_click.read().for_each(|event| {
         let Ok(window) = windows.get(event.window) else {
             return;
@@ -136,13 +282,17 @@ fn click_event(
         if let Some(pos) = intersect_ray_with_z_zero(ray.origin, ray.direction) {
             events.send(MapClickEvent {
                 translation: pos,
+                screen_position: position,
                 button: event.button,
+                state: event.state,
+                queued,
+                from_player: true,
             });
         }
     });
 }
 
-fn intersect_ray_with_z_zero(origin: Vec3, direction: Vec3) -> Option<Vec3> {
+pub fn intersect_ray_with_z_zero(origin: Vec3, direction: Vec3) -> Option<Vec3> {
     if direction.z == 0.0 {
         // The ray is parallel to the plane, check if it lies on the plane
         if origin.z == 0.0 {
@@ -165,39 +315,106 @@ pub struct DogSound;
 
 fn command_dog(
     mut cmd: Commands,
-    mut dogs: Query<(Entity, Option<&mut MoveTo>), With<DogTag>>,
+    dogs: Query<&DogIndex, (With<DogTag>, With<Selected>)>,
     mut click_events: EventReader<MapClickEvent>,
+    mut input: ResMut<DogInput>,
     dog_sounds: Query<With<DogSound>>,
     server: Res<AssetServer>,
     volume: Res<GameSettings>,
 ) {
-    click_events.read().for_each(|event| {
-        dogs.iter_mut().for_each(|(ent, move_to)| {
-            if event.button() == MouseButton::Right {
-                if let Some(mut move_to) = move_to {
-                    move_to.set(event.translation().truncate());
-                } else {
-                    cmd.entity(ent)
-                        .insert(MoveTo::new(event.translation().truncate()));
-                }
-
-                let random = rand::random::<usize>() % DOG_SOUNDS.len();
-
-                if dog_sounds.iter().count() > 0 {
-                    return;
-                }
-
-                cmd.spawn(AudioBundle {
-                    source: server.load(DOG_SOUNDS[random]),
-                    settings: PlaybackSettings {
-                        mode: PlaybackMode::Despawn,
-                        volume: Volume::Absolute(VolumeLevel::new(volume.effects * 2.)),
-                        ..default()
-                    },
-                    ..default()
-                })
-                .insert(DogSound);
+    let selected = selected_indices(dogs.iter());
+    if selected.is_empty() {
+        click_events.clear();
+        return;
+    }
+
+    let mut sound_playing = dog_sounds.iter().count() > 0;
+
+    click_events
+        .read()
+        .filter(|event| event.pressed() && event.button() == MouseButton::Right)
+        .for_each(|event| {
+            input.push(DogCommand::Move {
+                dogs: selected.clone(),
+                target: event.translation().truncate().into(),
+                queued: event.queued(),
+            });
+
+            if event.from_player() && !sound_playing {
+                play_dog_sound(&mut cmd, &server, &volume);
+                sound_playing = true;
             }
         });
+}
+
+pub fn apply_dog_input(
+    mut cmd: Commands,
+    mut dogs: Query<
+        (
+            Entity,
+            &DogIndex,
+            Option<&mut MoveTo>,
+            &mut Waypoints,
+            &mut DogGait,
+            &mut Steering,
+        ),
+        With<DogTag>,
+    >,
+    mut input: ResMut<DogInput>,
+    mut barks: EventWriter<BarkEvent>,
+) {
+    input.pending.drain(..).for_each(|command| {
+        let addressed = match &command {
+            DogCommand::Move { dogs, .. }
+            | DogCommand::Steer { dogs, .. }
+            | DogCommand::Gait { dogs, .. }
+            | DogCommand::Bark { dogs } => dogs,
+        };
+
+        dogs.iter_mut()
+            .filter(|(_, index, ..)| addressed.contains(&index.0))
+            .for_each(
+                |(entity, _, move_to, mut waypoints, mut gait, mut steering)| match &command {
+                    DogCommand::Move { target, queued, .. } => {
+                        let target = Vec2::from(*target);
+
+                        if !queued {
+                            waypoints.clear();
+                        }
+
+                        match (move_to, queued) {
+                            (Some(_), true) => waypoints.push(target),
+                            (Some(mut move_to), false) => {
+                                move_to.set(target);
+                            }
+                            (None, _) => {
+                                cmd.entity(entity).insert(MoveTo::new(target));
+                            }
+                        }
+                    }
+                    DogCommand::Steer { direction, .. } => {
+                        steering.0 = Vec2::from(*direction);
+                    }
+                    DogCommand::Gait { gait: next, .. } => {
+                        gait.set_if_neq(*next);
+                    }
+                    DogCommand::Bark { .. } => barks.send(BarkEvent { dog: entity }),
+                },
+            );
     });
 }
+
+pub fn play_dog_sound(cmd: &mut Commands, server: &AssetServer, volume: &GameSettings) {
+    let random = rand::random::<usize>() % DOG_SOUNDS.len();
+
+    cmd.spawn(AudioBundle {
+        source: server.load(DOG_SOUNDS[random]),
+        settings: PlaybackSettings {
+            mode: PlaybackMode::Despawn,
+            volume: Volume::Absolute(VolumeLevel::new(volume.effects * 2.)),
+            ..default()
+        },
+        ..default()
+    })
+    .insert(DogSound);
+}
@@ -0,0 +1,229 @@
+use std::time::Duration;
+
+use bevy::{
+    asset::{AssetLoader, AsyncReadExt},
+    prelude::*,
+};
+use bevy_rapier2d::{
+    dynamics::{RigidBody, Velocity},
+    geometry::Collider,
+};
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::{
+    animals::{animations::AnimalState, sheep::SheepTag},
+    level::LossCause,
+    simulation::Interpolated,
+    state::{AllowedState, GameState},
+    util::LifeTime,
+};
+
+pub const PARTICLE_MESH: Handle<Mesh> = Handle::weak_from_u128(98213659827365981273465);
+
+const PARTICLE_LIFETIME: Duration = Duration::from_millis(700);
+const GRAVITY: f32 = 40.;
+
+pub struct DeathPlugin;
+impl Plugin for DeathPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<DeathStyle>();
+        app.register_asset_loader(DeathStyleLoader);
+        app.add_systems(Update, (start_dying, play_dying, move_particles).chain());
+
+        let mut meshes = app.world.get_resource_mut::<Assets<Mesh>>().unwrap();
+        meshes.insert(PARTICLE_MESH, Mesh::from(shape::Cube { size: 0.5 }));
+    }
+}
+
+pub struct DeathStyleLoader;
+impl AssetLoader for DeathStyleLoader {
+    type Asset = DeathStyle;
+    type Settings = ();
+    type Error = anyhow::Error;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut bevy::asset::io::Reader,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut bevy::asset::LoadContext,
+    ) -> bevy::utils::BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            ron::de::from_bytes(&bytes).map_err(|e| anyhow::anyhow!("{}", e))
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["death.ron"]
+    }
+}
+
+/// How an animal goes, depending on what got it. One `.death.ron` per cause.
+#[derive(Asset, TypePath, Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct DeathStyle {
+    pub cause: Option<LossCause>,
+    /// seconds
+    pub duration: f32,
+    /// how deep the body sinks into the ground over the whole sequence
+    pub sink_depth: f32,
+    /// emissive rgb the body heats up to
+    pub burn: Option<(f32, f32, f32)>,
+    /// share of the sequence at the end spent fading out
+    pub fade: f32,
+    pub particles: usize,
+    pub particle_color: (f32, f32, f32),
+}
+
+impl Default for DeathStyle {
+    fn default() -> Self {
+        Self {
+            cause: None,
+            duration: 0.5,
+            sink_depth: 0.,
+            burn: None,
+            fade: 1.,
+            particles: 0,
+            particle_color: (1., 1., 1.),
+        }
+    }
+}
+
+fn find_style(styles: &Assets<DeathStyle>, cause: LossCause) -> DeathStyle {
+    styles
+        .iter()
+        .map(|(_, style)| style)
+        .find(|style| style.cause == Some(cause))
+        .cloned()
+        .unwrap_or_default()
+}
+
+#[derive(Component)]
+pub struct Dying {
+    cause: LossCause,
+    elapsed: f32,
+}
+
+#[derive(Component)]
+struct Particle(Vec3);
+
+/// Takes the sheep out of the flock and the physics and lets it die in style.
+pub fn kill(cmd: &mut Commands, entity: Entity, cause: LossCause) {
+    cmd.entity(entity)
+        .insert(Dying { cause, elapsed: 0. })
+        .insert(AnimalState::Dead)
+        .remove::<(Velocity, SheepTag, Collider, RigidBody, Interpolated)>();
+}
+
+fn start_dying(
+    mut cmd: Commands,
+    dying: Query<(Entity, &Dying, &Transform), Added<Dying>>,
+    children: Query<&Children>,
+    mut handles: Query<&mut Handle<StandardMaterial>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    styles: Res<Assets<DeathStyle>>,
+) {
+    let mut rng = rand::thread_rng();
+
+    dying.iter().for_each(|(entity, dying, transform)| {
+        let style = find_style(&styles, dying.cause);
+        cmd.entity(entity)
+            .insert(LifeTime::new(Duration::from_secs_f32(
+                style.duration.max(0.),
+            )));
+
+        // the model shares its materials with every other sheep, so burn and fade a copy
+        children.iter_descendants(entity).for_each(|child| {
+            let Ok(mut handle) = handles.get_mut(child) else {
+                return;
+            };
+            let Some(mut material) = materials.get(handle.as_ref()).cloned() else {
+                return;
+            };
+            material.alpha_mode = AlphaMode::Blend;
+            *handle = materials.add(material);
+        });
+
+        if style.particles == 0 {
+            return;
+        }
+
+        let particle_material = materials.add(StandardMaterial {
+            base_color: Color::rgb(
+                style.particle_color.0,
+                style.particle_color.1,
+                style.particle_color.2,
+            ),
+            unlit: true,
+            ..default()
+        });
+
+        (0..style.particles).for_each(|_| {
+            let direction = Vec2::from_angle(rng.gen_range(0.0..std::f32::consts::TAU));
+            let velocity = (direction * rng.gen_range(5.0..15.0)).extend(rng.gen_range(10.0..25.0));
+
+            cmd.spawn(PbrBundle {
+                mesh: PARTICLE_MESH,
+                material: particle_material.clone(),
+                transform: Transform::from_translation(transform.translation + Vec3::Z * 2.),
+                ..default()
+            })
+            .insert(Particle(velocity))
+            .insert(LifeTime::new(PARTICLE_LIFETIME))
+            .insert(AllowedState::new(GameState::Game));
+        });
+    });
+}
+
+fn play_dying(
+    mut dying: Query<(Entity, &mut Dying, &mut Transform)>,
+    children: Query<&Children>,
+    handles: Query<&Handle<StandardMaterial>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    styles: Res<Assets<DeathStyle>>,
+    time: Res<Time>,
+) {
+    dying
+        .iter_mut()
+        .for_each(|(entity, mut dying, mut transform)| {
+            let style = find_style(&styles, dying.cause);
+            dying.elapsed += time.delta_seconds();
+
+            let progress = (dying.elapsed / style.duration.max(f32::EPSILON)).min(1.);
+            let fade_start = 1. - style.fade.clamp(0., 1.);
+            let fade = ((progress - fade_start) / style.fade.max(f32::EPSILON)).clamp(0., 1.);
+
+            transform.translation.z = -style.sink_depth * progress * progress;
+
+            children.iter_descendants(entity).for_each(|child| {
+                let Some(material) = handles
+                    .get(child)
+                    .ok()
+                    .and_then(|handle| materials.get_mut(handle))
+                else {
+                    return;
+                };
+
+                // glow up until the fade starts
+                if let Some((r, g, b)) = style.burn {
+                    material.emissive =
+                        Color::rgb(r, g, b) * (progress / fade_start.max(f32::EPSILON)).min(1.);
+                }
+                material.base_color.set_a(1. - fade);
+            });
+        });
+}
+
+fn move_particles(mut particles: Query<(&mut Particle, &mut Transform)>, time: Res<Time>) {
+    let delta = time.delta_seconds();
+
+    particles
+        .iter_mut()
+        .for_each(|(mut particle, mut transform)| {
+            particle.0.z -= GRAVITY * delta;
+            transform.translation += particle.0 * delta;
+            transform.scale = (transform.scale - Vec3::splat(delta)).max(Vec3::ZERO);
+        });
+}
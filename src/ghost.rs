@@ -0,0 +1,363 @@
+use bevy::{gltf::Gltf, prelude::*, utils::HashMap};
+use bevy_rapier2d::dynamics::Velocity;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    animals::{
+        animations::{AnimalState, AnimationNames},
+        dog::DogTag,
+    },
+    herding::HerdingBot,
+    level::{
+        loader::LevelAsset,
+        progress::{LevelLost, LevelWon},
+        LevelLoaded, Score,
+    },
+    selection::DogIndex,
+    simulation::{Interpolated, SimulationSet, SimulationTick, TICK_RATE},
+    state::{AllowedState, GameState},
+    util::{restyle_new_materials, NewMaterials},
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+const GHOST_DIR: &str = "ghosts";
+const GHOST_ALPHA: f32 = 0.35;
+
+/// Races the player against their best run of the level: a see-through dog
+/// repeats its path tick by tick and the HUD compares the saved sheep.
+pub struct GhostPlugin;
+impl Plugin for GhostPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GhostRuns>();
+        app.add_systems(OnEnter(GameState::Game), spawn_ghost_label);
+        app.add_systems(FixedUpdate, begin_run.in_set(SimulationSet::Prepare));
+        app.add_systems(FixedUpdate, move_ghosts.in_set(SimulationSet::Gameplay));
+        app.add_systems(FixedUpdate, record_run.in_set(SimulationSet::Record));
+        app.add_systems(
+            Update,
+            (finish_run, fade_ghosts, update_ghost_label).run_if(in_state(GameState::Game)),
+        );
+    }
+}
+
+/// Where the dogs were and how many sheep were saved after one tick.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct GhostFrame {
+    /// by `DogIndex`
+    pub dogs: Vec<(f32, f32)>,
+    pub saved: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct GhostRun {
+    pub won: bool,
+    pub frames: Vec<GhostFrame>,
+}
+
+impl GhostRun {
+    fn saved(&self) -> usize {
+        self.frames.last().map_or(0, |frame| frame.saved)
+    }
+
+    fn beats(&self, other: &GhostRun) -> bool {
+        (self.won, self.saved(), std::cmp::Reverse(self.frames.len()))
+            > (
+                other.won,
+                other.saved(),
+                std::cmp::Reverse(other.frames.len()),
+            )
+    }
+
+    /// The frame for the given tick, the last one once the run is over.
+    fn frame(&self, tick: u64) -> Option<&GhostFrame> {
+        let index = (tick.max(1) - 1) as usize;
+        self.frames.get(index).or(self.frames.last())
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct GhostRuns {
+    best: HashMap<String, GhostRun>,
+    level: Option<String>,
+    recording: Option<GhostRun>,
+}
+
+impl GhostRuns {
+    fn current(&self) -> Option<&GhostRun> {
+        self.level.as_ref().and_then(|level| self.best.get(level))
+    }
+}
+
+#[derive(Component)]
+struct GhostDog(usize);
+
+#[derive(Component)]
+struct GhostLabel;
+
+fn begin_run(
+    mut cmd: Commands,
+    mut runs: ResMut<GhostRuns>,
+    level: Query<(Entity, &Handle<LevelAsset>), Added<LevelLoaded>>,
+    dogs: Query<
+        (
+            &DogIndex,
+            &Handle<Scene>,
+            &Handle<Gltf>,
+            &Transform,
+            Option<&AnimationNames>,
+        ),
+        With<DogTag>,
+    >,
+    levels: Res<Assets<LevelAsset>>,
+    server: Res<AssetServer>,
+) {
+    let Ok((entity, handle)) = level.get_single() else {
+        return;
+    };
+
+    // levels picked from disk have no path, their name has to do
+    let key = server
+        .get_path(handle.id())
+        .map(|path| path.to_string())
+        .or_else(|| levels.get(handle).map(|level| level.name.clone()));
+
+    let Some(key) = key else {
+        runs.level = None;
+        runs.recording = None;
+        return;
+    };
+
+    if !runs.best.contains_key(&key) {
+        if let Some(run) = load(&key) {
+            runs.best.insert(key.clone(), run);
+        }
+    }
+
+    runs.level = Some(key);
+    runs.recording = Some(GhostRun::default());
+
+    let Some(first) = runs.current().and_then(|run| run.frames.first()) else {
+        return;
+    };
+
+    dogs.iter()
+        .filter(|(index, ..)| index.0 < first.dogs.len())
+        .for_each(|(index, scene, gltf, transform, names)| {
+            let mut ghost = cmd.spawn(SceneBundle {
+                scene: scene.clone(),
+                transform: *transform,
+                ..default()
+            });
+
+            ghost
+                .insert(gltf.clone())
+                .insert(GhostDog(index.0))
+                .insert(AnimalState::Idle)
+                .insert(Velocity::default())
+                .insert(Interpolated::default())
+                .insert(Name::new("ghost"))
+                .insert(AllowedState::new(GameState::Game))
+                .set_parent(entity);
+
+            if let Some(names) = names {
+                ghost.insert(names.clone());
+            }
+        });
+}
+
+fn move_ghosts(
+    mut ghosts: Query<(&GhostDog, &mut Transform, &mut Velocity)>,
+    runs: Res<GhostRuns>,
+    tick: Res<SimulationTick>,
+) {
+    let Some(run) = runs.current() else {
+        return;
+    };
+
+    let position = |tick: u64, dog: usize| {
+        run.frame(tick)
+            .and_then(|frame| frame.dogs.get(dog))
+            .map(|position| Vec2::from(*position))
+    };
+
+    ghosts
+        .iter_mut()
+        .for_each(|(ghost, mut transform, mut velocity)| {
+            let Some(current) = position(tick.0, ghost.0) else {
+                return;
+            };
+            let previous = position(tick.0.saturating_sub(1), ghost.0).unwrap_or(current);
+
+            transform.translation = current.extend(transform.translation.z);
+            // drives the facing and the walk and run clips like on the real dog
+            velocity.linvel = (current - previous) * TICK_RATE as f32;
+        });
+}
+
+fn record_run(
+    mut runs: ResMut<GhostRuns>,
+    dogs: Query<(&DogIndex, &Transform), With<DogTag>>,
+    score: Res<Score>,
+) {
+    let Some(run) = runs.recording.as_mut() else {
+        return;
+    };
+
+    let mut positions = dogs
+        .iter()
+        .map(|(index, transform)| (index.0, transform.translation.truncate().into()))
+        .collect::<Vec<(usize, (f32, f32))>>();
+    positions.sort_by_key(|(index, _)| *index);
+
+    run.frames.push(GhostFrame {
+        dogs: positions
+            .into_iter()
+            .map(|(_, position)| position)
+            .collect(),
+        saved: score.saved,
+    });
+}
+
+fn finish_run(
+    mut runs: ResMut<GhostRuns>,
+    mut won: EventReader<LevelWon>,
+    mut lost: EventReader<LevelLost>,
+    bot: Res<HerdingBot>,
+) {
+    let won = won.read().count() > 0;
+    let lost = lost.read().count() > 0;
+
+    if !won && !lost {
+        return;
+    }
+
+    let Some(mut run) = runs.recording.take() else {
+        return;
+    };
+
+    // only the player's own runs count
+    if bot.is_active() {
+        return;
+    }
+
+    let Some(level) = runs.level.clone() else {
+        return;
+    };
+
+    run.won = won;
+
+    if runs.best.get(&level).is_some_and(|best| !run.beats(best)) {
+        return;
+    }
+
+    info!("new best run on {}, {} sheep saved", level, run.saved());
+    save(&level, &run);
+    runs.best.insert(level, run);
+}
+
+fn fade_ghosts(
+    mut handles: NewMaterials,
+    parents: Query<&Parent>,
+    ghosts: Query<&GhostDog>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut faded: Local<HashMap<(AssetId<StandardMaterial>, ()), Handle<StandardMaterial>>>,
+) {
+    restyle_new_materials(
+        &mut handles,
+        &parents,
+        &ghosts,
+        &mut materials,
+        &mut faded,
+        |_| Some(()),
+        |_, material| {
+            material.alpha_mode = AlphaMode::Blend;
+            material.base_color.set_a(GHOST_ALPHA);
+        },
+    );
+}
+
+fn spawn_ghost_label(mut cmd: Commands) {
+    cmd.spawn(TextBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            right: Val::Px(10.),
+            top: Val::Px(10.),
+            ..default()
+        },
+        text: Text::from_section(
+            "",
+            TextStyle {
+                font_size: 20.,
+                color: Color::WHITE.with_a(0.8),
+                ..default()
+            },
+        ),
+        ..default()
+    })
+    .insert(GhostLabel)
+    .insert(AllowedState::new(GameState::Game));
+}
+
+fn update_ghost_label(
+    mut query: Query<&mut Text, With<GhostLabel>>,
+    runs: Res<GhostRuns>,
+    score: Res<Score>,
+    tick: Res<SimulationTick>,
+) {
+    let label = runs
+        .current()
+        .and_then(|run| run.frame(tick.0))
+        .map(|frame| {
+            let lead = score.saved as i64 - frame.saved as i64;
+            format!(
+                "Best run: {} saved  You: {} ({:+})",
+                frame.saved, score.saved, lead
+            )
+        })
+        .unwrap_or_default();
+
+    query.iter_mut().for_each(|mut text| {
+        if text.sections[0].value != label {
+            text.sections[0].value = label.clone();
+        }
+    });
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn ghost_path(level: &str) -> std::path::PathBuf {
+    let name = level.replace(['/', '\\', ':'], "-");
+    std::path::Path::new(GHOST_DIR).join(format!("{}.ghost.ron", name))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn load(level: &str) -> Option<GhostRun> {
+    let text = std::fs::read_to_string(ghost_path(level)).ok()?;
+    ron::from_str(&text)
+        .map_err(|e| warn!("failed to read ghost of {}: {}", level, e))
+        .ok()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn load(_level: &str) -> Option<GhostRun> {
+    None
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save(level: &str, run: &GhostRun) {
+    let path = ghost_path(level);
+    let result = ron::to_string(run)
+        .map_err(|e| anyhow::anyhow!("{}", e))
+        .and_then(|text| {
+            std::fs::create_dir_all(GHOST_DIR)?;
+            std::fs::write(&path, text)?;
+            Ok(())
+        });
+
+    if let Err(e) = result {
+        error!("failed to save ghost {}: {}", path.display(), e);
+    }
+}
+
+// the browser build keeps the best runs for the session only
+#[cfg(target_arch = "wasm32")]
+fn save(_level: &str, _run: &GhostRun) {}
@@ -1,31 +1,47 @@
-use std::time::Duration;
-
 use bevy::{
     audio::{PlaybackMode, Volume, VolumeLevel},
     prelude::*,
+    render::{mesh::Indices, render_resource::PrimitiveTopology},
     utils::HashSet,
 };
 use bevy_rapier2d::{
-    dynamics::Velocity,
-    geometry::{Collider, Sensor},
+    dynamics::{RigidBody, Velocity},
+    geometry::{Collider, CollisionGroups, Sensor},
     plugin::RapierContext,
 };
 
 use crate::{
     animals::{animations::AnimalState, sheep::SheepTag},
+    collision,
     level::Score,
-    util::LifeTime,
+    simulation::SimulationSet,
+    state::{AllowedState, GameState},
     GameSettings,
 };
 
 pub const SUCCESS_GLOW: Handle<StandardMaterial> = Handle::weak_from_u128(12561396483470153565671);
 pub const FAIL_GLOW: Handle<StandardMaterial> = Handle::weak_from_u128(125613964543455646571);
 pub const GLOW_MESH: Handle<Mesh> = Handle::weak_from_u128(126565623323232325651);
+pub const BEAM_MATERIAL: Handle<StandardMaterial> = Handle::weak_from_u128(126565623398712309871);
+pub const BEAM_MESH: Handle<Mesh> = Handle::weak_from_u128(126565623387162398123);
+
+// seconds from touching the goal to vanishing into the ship
+const ABDUCTION_TIME: f32 = 1.2;
+// the sheep disappears a bit below the ship's center, into the hatch
+const HATCH_OFFSET: f32 = 2.;
+const BEAM_RADIUS: f32 = 4.;
 
 pub struct GoalPlugin;
 impl Plugin for GoalPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, watch_goal_enter);
+        app.add_event::<SheepAbducted>();
+        app.add_systems(
+            FixedUpdate,
+            (watch_goal_enter, abduct)
+                .chain()
+                .in_set(SimulationSet::Gameplay),
+        );
+        app.add_systems(Update, update_beams);
 
         let mut materials = app
             .world
@@ -43,20 +59,77 @@ impl Plugin for GoalPlugin {
 
         materials.insert(SUCCESS_GLOW, success_glow);
         materials.insert(FAIL_GLOW, fail_glow);
+        materials.insert(
+            BEAM_MATERIAL,
+            StandardMaterial {
+                base_color: Color::rgba(0.4, 3.0, 0.6, 0.25),
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                double_sided: true,
+                cull_mode: None,
+                ..default()
+            },
+        );
 
         let mut meshes = app.world.get_resource_mut::<Assets<Mesh>>().unwrap();
 
         let glow_mesh = Mesh::from(shape::Quad::new(Vec2::new(1., 1.)));
         meshes.insert(GLOW_MESH, glow_mesh);
+        meshes.insert(BEAM_MESH, beam_mesh(16));
     }
 }
 
+/// Open cone with its tip at the origin and a unit radius base one unit below.
+fn beam_mesh(segments: usize) -> Mesh {
+    let mut positions = Vec::with_capacity(segments * 3);
+    let mut normals = Vec::with_capacity(segments * 3);
+    let mut uvs = Vec::with_capacity(segments * 3);
+
+    (0..segments).for_each(|i| {
+        let a = Vec2::from_angle(i as f32 / segments as f32 * std::f32::consts::TAU);
+        let b = Vec2::from_angle((i + 1) as f32 / segments as f32 * std::f32::consts::TAU);
+        let normal = (a + b).normalize().extend(1.).normalize();
+
+        positions.extend([[0., 0., 0.], [a.x, a.y, -1.], [b.x, b.y, -1.]]);
+        normals.extend([normal.to_array(); 3]);
+        uvs.extend([[0.5, 0.], [0., 1.], [1., 1.]]);
+    });
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.set_indices(Some(Indices::U32((0..segments as u32 * 3).collect())));
+    mesh
+}
+
 #[derive(Component)]
 pub struct GoalTag;
 
 #[derive(Component)]
 pub struct GoalSound;
 
+#[derive(Component)]
+pub struct UfoTag;
+
+/// A rescued sheep on its way up the tractor beam.
+#[derive(Component)]
+pub struct Abducting {
+    start: Vec3,
+    /// the species scale, shrunk from as the sheep rises
+    scale: Vec3,
+    elapsed: f32,
+}
+
+#[derive(Component)]
+pub struct AbductionBeam(Entity);
+
+/// Fired once a rescued sheep is inside the ship.
+#[derive(Event)]
+pub struct SheepAbducted {
+    pub sheep: Entity,
+}
+
 #[derive(Bundle)]
 pub struct GoalBundle {
     pub mesh: Handle<Mesh>,
@@ -70,6 +143,7 @@ pub struct GoalBundle {
     pub name: Name,
     pub sensor: Sensor,
     pub collider: Collider,
+    pub collision_groups: CollisionGroups,
 }
 
 impl Default for GoalBundle {
@@ -86,16 +160,15 @@ impl Default for GoalBundle {
             goal: GoalTag,
             sensor: Sensor,
             collider: Collider::default(),
+            collision_groups: collision::sensor(),
         }
     }
 }
 
 fn watch_goal_enter(
     mut cmd: Commands,
-    mut score: ResMut<Score>,
-    _meshes: ResMut<Assets<Mesh>>,
     goals: Query<Entity, With<GoalTag>>,
-    sheeps: Query<Entity, With<SheepTag>>,
+    transforms: Query<&Transform>,
     rapier_context: Res<RapierContext>,
     server: Res<AssetServer>,
     sheep_sound: Query<With<GoalSound>>,
@@ -105,17 +178,25 @@ fn watch_goal_enter(
     goals.iter().for_each(|entity| {
         rapier_context
             .intersections_with(entity)
+            .filter(|(_, _, intersecting)| *intersecting)
             .for_each(|(a, b, _)| {
+                // the sensor layer only ever intersects with sheep
                 let sheep_ent = if a == entity { b } else { a };
-                if sheeps.get(sheep_ent).is_err() {
+                if !escorted_sheep.insert(sheep_ent) {
                     return;
                 }
+                let Ok(transform) = transforms.get(sheep_ent) else {
+                    return;
+                };
 
                 cmd.entity(sheep_ent)
-                    .insert(LifeTime::new(Duration::from_millis(200)))
+                    .insert(Abducting {
+                        start: transform.translation,
+                        scale: transform.scale,
+                        elapsed: 0.,
+                    })
                     .insert(AnimalState::Jumping)
-                    .remove::<Velocity>()
-                    .remove::<SheepTag>()
+                    .remove::<(Velocity, SheepTag, Collider, RigidBody)>()
                     .with_children(|cmd| {
                         cmd.spawn(MaterialMeshBundle {
                             mesh: GLOW_MESH.clone(),
@@ -124,7 +205,15 @@ fn watch_goal_enter(
                         });
                     });
 
-                escorted_sheep.insert(sheep_ent);
+                cmd.spawn(MaterialMeshBundle {
+                    mesh: BEAM_MESH.clone(),
+                    material: BEAM_MATERIAL.clone(),
+                    ..default()
+                })
+                .insert(AbductionBeam(sheep_ent))
+                .insert(AllowedState::new(GameState::Game))
+                .insert(Name::new("beam"));
+
                 if sheep_sound.iter().count() > 2 {
                     return;
                 }
@@ -141,5 +230,79 @@ fn watch_goal_enter(
                 .insert(GoalSound);
             })
     });
-    score.saved += escorted_sheep.len();
+}
+
+fn abduct(
+    mut cmd: Commands,
+    mut sheeps: Query<(Entity, &mut Abducting, &mut Transform)>,
+    ufos: Query<&GlobalTransform, With<UfoTag>>,
+    mut score: ResMut<Score>,
+    mut abducted: EventWriter<SheepAbducted>,
+    time: Res<Time>,
+) {
+    sheeps
+        .iter_mut()
+        .for_each(|(entity, mut abducting, mut transform)| {
+            abducting.elapsed += time.delta_seconds();
+
+            let hatch = nearest_ufo(&ufos, abducting.start)
+                .unwrap_or(abducting.start + Vec3::Z * 30.)
+                - Vec3::Z * HATCH_OFFSET;
+
+            let progress = (abducting.elapsed / ABDUCTION_TIME).min(1.);
+            // slow lift off, then sucked in
+            let eased = progress * progress;
+
+            transform.translation = abducting.start.lerp(hatch, eased);
+            transform.rotate_z(time.delta_seconds() * 6. * progress);
+            transform.scale = abducting.scale * (1. - 0.6 * eased);
+
+            if progress < 1. {
+                return;
+            }
+
+            cmd.entity(entity).despawn_recursive();
+            score.saved += 1;
+            abducted.send(SheepAbducted { sheep: entity });
+        });
+}
+
+fn update_beams(
+    mut cmd: Commands,
+    mut beams: Query<(Entity, &AbductionBeam, &mut Transform)>,
+    sheeps: Query<&Transform, (With<Abducting>, Without<AbductionBeam>)>,
+    ufos: Query<&GlobalTransform, With<UfoTag>>,
+    mut abducted: EventReader<SheepAbducted>,
+) {
+    let arrived = abducted.read().map(|event| event.sheep).collect::<Vec<_>>();
+
+    beams.iter_mut().for_each(|(entity, beam, mut transform)| {
+        let Some(sheep) = sheeps
+            .get(beam.0)
+            .ok()
+            .filter(|_| !arrived.contains(&beam.0))
+        else {
+            cmd.entity(entity).despawn_recursive();
+            return;
+        };
+
+        let Some(ufo) = nearest_ufo(&ufos, sheep.translation) else {
+            return;
+        };
+
+        // from the ship's belly down to the ground below the sheep
+        let ground = sheep.translation.truncate().extend(0.);
+        let length = (ufo - ground).length();
+
+        *transform = Transform::from_translation(ufo)
+            .looking_to(ground - ufo, Vec3::Y)
+            .with_scale(Vec3::new(BEAM_RADIUS, BEAM_RADIUS, length));
+    });
+}
+
+fn nearest_ufo(ufos: &Query<&GlobalTransform, With<UfoTag>>, position: Vec3) -> Option<Vec3> {
+    ufos.iter().map(|ufo| ufo.translation()).min_by(|a, b| {
+        a.distance_squared(position)
+            .total_cmp(&b.distance_squared(position))
+    })
 }
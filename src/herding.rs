@@ -0,0 +1,341 @@
+use bevy::{app::AppExit, prelude::*};
+
+use crate::{
+    animals::{dog::DogTag, sheep::SheepTag},
+    controls::MapClickEvent,
+    goal::GoalTag,
+    level::{
+        loader::LevelAsset,
+        progress::{LevelLost, LevelWon},
+        tilemap::TileMap,
+        Levels, TILE_SIZE,
+    },
+    menu::DemoButton,
+    state::GameState,
+};
+
+// roughly the distance between two sheep in a calm flock
+const SHEEP_SPACING: f32 = 3.;
+// how many tiles along the flock's route the drive aims at
+const DRIVE_LOOKAHEAD: usize = 3;
+const ORDER_INTERVAL: f32 = 0.4;
+const DEMO_PAUSE: f32 = 3.;
+const VERIFY_TIME_LIMIT: f32 = 300.;
+
+/// Lets the dog herd on its own, following Strömbom et al. (2014): collect
+/// the sheep furthest from the flock until it is compact, then drive the
+/// flock from behind towards the goal.
+pub struct HerdingPlugin;
+impl Plugin for HerdingPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(HerdingBot::from_args());
+        app.add_systems(OnEnter(GameState::Menu), start_verification);
+        app.add_systems(Update, start_demo.run_if(in_state(GameState::Menu)));
+        app.add_systems(
+            Update,
+            (
+                toggle_assist,
+                interrupt_demo,
+                herd,
+                level_over,
+                verify_timeout,
+            )
+                .run_if(in_state(GameState::Game)),
+        );
+    }
+}
+
+#[derive(Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BotMode {
+    #[default]
+    Off,
+    /// Toggled with `H` while playing.
+    Assist,
+    /// Attract mode started from the menu, loops through the campaign.
+    Demo,
+    /// `--verify [level]`, plays the campaign or a single level and exits.
+    Verify,
+}
+
+#[derive(Resource)]
+pub struct HerdingBot {
+    pub mode: BotMode,
+    verify_level: Option<String>,
+    /// set once the first level was loaded, coming back to the menu means it failed to start
+    verifying: bool,
+    order_timer: Timer,
+    level_time: f32,
+    next_level_in: Option<Timer>,
+}
+
+impl Default for HerdingBot {
+    fn default() -> Self {
+        Self {
+            mode: BotMode::Off,
+            verify_level: None,
+            verifying: false,
+            order_timer: Timer::from_seconds(ORDER_INTERVAL, TimerMode::Repeating),
+            level_time: 0.,
+            next_level_in: None,
+        }
+    }
+}
+
+impl HerdingBot {
+    fn from_args() -> Self {
+        let mut args = std::env::args().skip_while(|arg| arg != "--verify");
+        if args.next().is_none() {
+            return Self::default();
+        }
+
+        Self {
+            mode: BotMode::Verify,
+            verify_level: args.next().filter(|arg| !arg.starts_with("--")),
+            ..default()
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.mode != BotMode::Off
+    }
+
+    fn load(
+        &mut self,
+        level: Handle<LevelAsset>,
+        levels: &mut Levels,
+        state: &mut NextState<GameState>,
+    ) {
+        self.level_time = 0.;
+        self.next_level_in = None;
+        levels.set(level);
+        state.set(GameState::Prepare);
+    }
+}
+
+fn start_verification(
+    mut bot: ResMut<HerdingBot>,
+    mut levels: ResMut<Levels>,
+    mut state: ResMut<NextState<GameState>>,
+    server: Res<AssetServer>,
+) {
+    if bot.mode != BotMode::Verify {
+        return;
+    }
+
+    if bot.verifying {
+        error!(
+            "verification failed, level {:?} could not be started",
+            levels.current()
+        );
+        std::process::exit(1);
+    }
+    bot.verifying = true;
+
+    let level = match &bot.verify_level {
+        Some(path) => server.load(path.clone()),
+        None => levels.first(),
+    };
+
+    info!("verifying {:?}", level);
+    bot.load(level, &mut levels, &mut state);
+}
+
+fn start_demo(
+    mut bot: ResMut<HerdingBot>,
+    mut levels: ResMut<Levels>,
+    mut state: ResMut<NextState<GameState>>,
+    buttons: Query<&Interaction, (With<DemoButton>, Changed<Interaction>)>,
+) {
+    if !buttons
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        return;
+    }
+
+    bot.mode = BotMode::Demo;
+    let first = levels.first();
+    bot.load(first, &mut levels, &mut state);
+}
+
+fn toggle_assist(mut bot: ResMut<HerdingBot>, inputs: Res<Input<KeyCode>>) {
+    if !inputs.just_pressed(KeyCode::H) {
+        return;
+    }
+
+    bot.mode = match bot.mode {
+        BotMode::Off => BotMode::Assist,
+        BotMode::Assist => BotMode::Off,
+        mode => mode,
+    };
+    info!("herding bot: {:?}", bot.mode);
+}
+
+fn interrupt_demo(
+    mut bot: ResMut<HerdingBot>,
+    mut state: ResMut<NextState<GameState>>,
+    inputs: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+) {
+    if bot.mode != BotMode::Demo {
+        return;
+    }
+
+    if inputs.get_just_pressed().next().is_none() && mouse.get_just_pressed().next().is_none() {
+        return;
+    }
+
+    bot.mode = BotMode::Off;
+    state.set(GameState::Menu);
+}
+
+fn herd(
+    mut bot: ResMut<HerdingBot>,
+    mut orders: EventWriter<MapClickEvent>,
+    sheeps: Query<&Transform, With<SheepTag>>,
+    dogs: Query<&Transform, With<DogTag>>,
+    goals: Query<&Transform, With<GoalTag>>,
+    tilemap: Res<TileMap>,
+    time: Res<Time>,
+) {
+    if !bot.is_active() || !bot.order_timer.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let positions: Vec<Vec2> = sheeps
+        .iter()
+        .map(|transform| transform.translation.truncate())
+        .collect();
+
+    if positions.is_empty() {
+        return;
+    }
+
+    let count = positions.len() as f32;
+    let centroid = positions.iter().sum::<Vec2>() / count;
+
+    let Some(goal) = goals
+        .iter()
+        .map(|transform| transform.translation.truncate())
+        .min_by(|a, b| a.distance(centroid).total_cmp(&b.distance(centroid)))
+    else {
+        return;
+    };
+
+    if dogs.is_empty() {
+        return;
+    }
+
+    let dog = dogs
+        .iter()
+        .map(|transform| transform.translation.truncate())
+        .sum::<Vec2>()
+        / dogs.iter().len() as f32;
+
+    let compact_radius = SHEEP_SPACING * count.powf(2. / 3.);
+
+    let Some(furthest) = positions
+        .iter()
+        .copied()
+        .max_by(|a, b| a.distance(centroid).total_cmp(&b.distance(centroid)))
+    else {
+        return;
+    };
+
+    let target = match furthest.distance(centroid) > compact_radius {
+        // collect: get behind the straggler, seen from the flock
+        true => furthest + (furthest - centroid).normalize_or_zero() * SHEEP_SPACING * 2.,
+        // drive: push the compact flock along its route to the goal
+        false => {
+            let route = tilemap.find_path(centroid, goal);
+            let heading = route
+                .get(DRIVE_LOOKAHEAD.min(route.len().saturating_sub(1)))
+                .copied()
+                .unwrap_or(goal);
+
+            centroid
+                + (centroid - heading).normalize_or_zero()
+                    * (SHEEP_SPACING * count.sqrt() + TILE_SIZE)
+        }
+    };
+
+    // don't cut through the flock on the way around it
+    let target =
+        match dog.distance(centroid) < compact_radius && (target - dog).dot(centroid - dog) > 0. {
+            true => dog + (dog - centroid).perp().normalize_or_zero() * TILE_SIZE * 2.,
+            false => target,
+        };
+
+    orders.send(MapClickEvent::order(target.extend(0.)));
+}
+
+fn level_over(
+    mut bot: ResMut<HerdingBot>,
+    mut won: EventReader<LevelWon>,
+    mut lost: EventReader<LevelLost>,
+    mut levels: ResMut<Levels>,
+    mut state: ResMut<NextState<GameState>>,
+    mut exit: EventWriter<AppExit>,
+    time: Res<Time>,
+) {
+    let won = won.read().count() > 0;
+    let lost = lost.read().count() > 0;
+
+    match bot.mode {
+        BotMode::Demo => {
+            if won || lost {
+                bot.next_level_in = Some(Timer::from_seconds(DEMO_PAUSE, TimerMode::Once));
+            }
+
+            let Some(timer) = bot.next_level_in.as_mut() else {
+                return;
+            };
+
+            if !timer.tick(time.delta()).finished() {
+                return;
+            }
+
+            let next = levels.next().unwrap_or_else(|| levels.first());
+            bot.load(next, &mut levels, &mut state);
+        }
+        BotMode::Verify => {
+            if lost {
+                error!("verification failed, level {:?} was lost", levels.current());
+                std::process::exit(1);
+            }
+
+            if !won {
+                return;
+            }
+
+            info!("level {:?} verified", levels.current());
+
+            match levels.next() {
+                Some(next) if bot.verify_level.is_none() => {
+                    bot.load(next, &mut levels, &mut state);
+                }
+                _ => {
+                    info!("verification passed");
+                    exit.send(AppExit);
+                }
+            }
+        }
+        _ => (),
+    }
+}
+
+fn verify_timeout(mut bot: ResMut<HerdingBot>, levels: Res<Levels>, time: Res<Time>) {
+    if bot.mode != BotMode::Verify {
+        return;
+    }
+
+    bot.level_time += time.delta_seconds();
+    if bot.level_time > VERIFY_TIME_LIMIT {
+        error!(
+            "verification failed, level {:?} not beaten within {} seconds",
+            levels.current(),
+            VERIFY_TIME_LIMIT
+        );
+        std::process::exit(1);
+    }
+}
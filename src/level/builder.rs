@@ -9,9 +9,18 @@ use bevy_tweening::{
 use rand::Rng;
 
 use crate::{
-    animals::{dog::DogBundle, llama::LLamaBundle, sheep::SheepBundle},
-    goal::GoalBundle,
+    animals::{
+        dog::DogBundle,
+        llama::{LLamaBundle, LlamaMovement},
+        sheep::SheepBundle,
+        species::{self, SpeciesAsset},
+        wolf::WolfBundle,
+    },
+    collision,
+    goal::{GoalBundle, UfoTag},
     level::{LevelBundle, TILE_SIZE},
+    selection::DogIndex,
+    simulation::{SimulationRng, SimulationTick},
     state::GameState,
     trap::TrapBundle,
     ui::Dialog,
@@ -19,6 +28,7 @@ use crate::{
 
 use super::{
     loader::{LevelAsset, Tiles},
+    tilemap::TileMap,
     LevelLoaded, Score, TileBundle,
 };
 
@@ -42,6 +52,7 @@ impl LoadLevelEvent {
 fn start_level(
     mut cmd: Commands,
     mut events: EventReader<LoadLevelEvent>,
+    current_state: Res<State<GameState>>,
     mut state: ResMut<NextState<GameState>>,
     mut score: ResMut<Score>,
 ) {
@@ -57,7 +68,10 @@ fn start_level(
         ..default()
     });
 
-    state.set(GameState::Game);
+    // a restart rebuilds the level in place, without leaving the game
+    if *current_state.get() != GameState::Game {
+        state.set(GameState::Game);
+    }
 }
 
 fn load_level(
@@ -70,18 +84,51 @@ fn load_level(
     mut dialog: Query<&mut Text, With<Dialog>>,
     mut score: ResMut<Score>,
     server: Res<AssetServer>,
+    species_assets: Res<Assets<SpeciesAsset>>,
+    mut tick: ResMut<SimulationTick>,
+    mut rng: ResMut<SimulationRng>,
 ) {
     query.iter().for_each(|(entity, handle)| {
         let Some(level) = levels.get(handle) else {
             return;
         };
 
+        let find_species = |name: &str| {
+            let found = species::find(&species_assets, name);
+            if found.is_none() {
+                warn!("unknown species {:?}", name);
+            }
+            found
+        };
+
+        // a level missing any of its animals can't be played, the flock least of all
+        let (Some(sheep_species), Some(dog_species), Some(llama_species), Some(wolf_species)) = (
+            find_species(&level.species.sheep),
+            find_species(&level.species.dog),
+            find_species(&level.species.llama),
+            find_species(&level.species.wolf),
+        ) else {
+            warn!("Failed to load species of {:?}", handle);
+            next_state.set(GameState::Menu);
+            return;
+        };
+
         let Some(data) = &level.parsed else {
             warn!("Failed to load from file {:?}", handle);
             next_state.set(GameState::Menu);
             return;
         };
 
+        let sheep_tiles = data
+            .iter()
+            .filter(|(_, tile)| matches!(tile, Tiles::Sheep))
+            .count();
+        if sheep_tiles * level.sheeps_per_spawn == 0 {
+            warn!("Level {:?} has no sheep", handle);
+            next_state.set(GameState::Menu);
+            return;
+        }
+
         let Ok(mut dialog) = dialog.get_single_mut() else {
             return;
         };
@@ -125,12 +172,16 @@ fn load_level(
             ..default()
         });
 
+        cmd.insert_resource(TileMap::from_layout(data));
+
         // -----------------------------------------------------------------------
         // Build Layout
         let mut sheep_spawn_count = 0;
+        let mut llama_spawn_count = 0;
+        let mut dog_spawn_count = 0;
         cmd.entity(entity).with_children(|cmd| {
             data.iter().for_each(|(pos, tile)| match tile {
-                Tiles::Empty | Tiles::Sheep | Tiles::Dog | Tiles::Llama => {
+                Tiles::Empty | Tiles::Sheep | Tiles::Dog | Tiles::Llama | Tiles::Wolf => {
                     cmd.spawn(TileBundle {
                         mesh: flat_mesh.clone(),
                         transform: Transform::from_translation(pos.extend(0.)),
@@ -138,41 +189,52 @@ fn load_level(
                         ..Default::default()
                     });
 
-                    if matches!(tile, Tiles::Dog) {
-                        let transform = Transform::from_translation(pos.extend(0.));
+                    if let (Tiles::Dog, species) = (tile, dog_species) {
                         cmd.spawn(DogBundle {
-                            scene: server.load("models/pug.glb#Scene0"),
-                            gltf: server.load("models/pug.glb"),
-                            transform,
+                            index: DogIndex(dog_spawn_count),
+                            transform: species.transform(*pos),
                             ..default()
-                        });
+                        })
+                        .insert(species.components(&server));
+                        dog_spawn_count += 1;
                     }
 
-                    if matches!(tile, Tiles::Llama) {
-                        let transform = Transform::from_translation(pos.extend(0.));
+                    if let (Tiles::Llama, species) = (tile, llama_species) {
+                        let route = level
+                            .llama_routes
+                            .get(llama_spawn_count)
+                            .cloned()
+                            .unwrap_or_default();
+                        llama_spawn_count += 1;
+
                         cmd.spawn(LLamaBundle {
-                            scene: server.load("models/llama.glb#Scene0"),
-                            gltf: server.load("models/llama.glb"),
-                            transform,
+                            movement: LlamaMovement::from_route(&route, *pos),
+                            transform: species.transform(*pos),
                             ..default()
-                        });
+                        })
+                        .insert(species.components(&server));
                     }
 
-                    if matches!(tile, Tiles::Sheep) {
+                    if let (Tiles::Wolf, species) = (tile, wolf_species) {
+                        cmd.spawn(WolfBundle {
+                            transform: species.transform(*pos),
+                            ..default()
+                        })
+                        .insert(species.components(&server));
+                    }
+
+                    if let (Tiles::Sheep, species) = (tile, sheep_species) {
                         sheep_spawn_count += 1;
                         let mut out = Vec::new();
                         circle_formation(2., 2., level.sheeps_per_spawn, &mut out);
                         (0..level.sheeps_per_spawn)
                             .zip(out.iter())
                             .for_each(|(_, offset)| {
-                                let transform =
-                                    Transform::from_translation(pos.extend(0.) + *offset);
                                 cmd.spawn(SheepBundle {
-                                    scene: server.load("models/sheep.glb#Scene0"),
-                                    gltf: server.load("models/sheep.glb"),
-                                    transform,
+                                    transform: species.transform(*pos + offset.truncate()),
                                     ..default()
-                                });
+                                })
+                                .insert(species.components(&server));
                                 // .insert(MoveTo::new(*pos));
                             });
                     }
@@ -185,6 +247,7 @@ fn load_level(
                         ..Default::default()
                     })
                     .insert(Collider::cuboid(TILE_SIZE / 2., TILE_SIZE / 2.))
+                    .insert(collision::wall())
                     .insert(RigidBody::Fixed);
                 }
                 Tiles::Trap => {
@@ -249,7 +312,9 @@ fn load_level(
                     .with_scale(Vec3::splat((acc_goal_pos.len() as f32).clamp(1., 3.))),
                 ..default()
             })
-            .insert(Animator::new(pos_tween));
+            .insert(Animator::new(pos_tween))
+            .insert(UfoTag)
+            .insert(Name::new("ufo"));
 
             // next state
             next_state.set(GameState::Game);
@@ -268,9 +333,13 @@ fn load_level(
             }),
             ..default()
         })
-        .insert(Name::new("Ground"));
+        .insert(Name::new("Ground"))
+        .set_parent(entity);
 
         cmd.entity(entity).insert(LevelLoaded);
+        // the simulation starts over with the first tick that sees the level
+        tick.0 = 0;
+        rng.start_level();
         dialog.sections[0].value = format!(
             "{} There are {} sheep. Escort at least {} % of the sheep.",
             level.intro.clone(),
@@ -306,7 +375,8 @@ fn load_level(
                 scene: server.load("models/tree.glb#Scene0"),
                 transform: Transform::from_translation(Vec3::new(x, y, 0.)),
                 ..default()
-            });
+            })
+            .set_parent(entity);
         }
     });
 }
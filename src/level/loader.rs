@@ -47,6 +47,10 @@ pub struct LevelAsset {
     pub loose: String,
     pub win_percent: f32,
     pub animal_behavior: Option<AnimalBehavior>,
+    #[serde(default)]
+    pub llama_routes: Vec<LlamaRoute>,
+    #[serde(default)]
+    pub species: SpeciesRoles,
     pub layout: String,
     #[serde(skip)]
     pub parsed: Option<Vec<(Vec2, Tiles)>>,
@@ -75,7 +79,39 @@ impl TryFrom<&[u8]> for LevelAsset {
     }
 }
 
+/// How the llama spawned on the n-th `L` tile (in reading order) moves.
+/// Waypoints and radii are given in tiles, `(line, column)` of the layout.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub enum LlamaRoute {
+    #[default]
+    Stationary,
+    Patrol(Vec<(f32, f32)>),
+    Wander(f32),
+}
+
+/// Which species, by name, fill the roles of the level.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct SpeciesRoles {
+    pub sheep: String,
+    pub dog: String,
+    pub llama: String,
+    pub wolf: String,
+}
+
+impl Default for SpeciesRoles {
+    fn default() -> Self {
+        Self {
+            sheep: "sheep".into(),
+            dog: "dog".into(),
+            llama: "llama".into(),
+            wolf: "wolf".into(),
+        }
+    }
+}
+
 #[derive(Deserialize, Debug)]
+#[serde(default)]
 pub struct AnimalBehavior {
     pub alignment: f32,
     pub cohesion: f32,
@@ -88,6 +124,40 @@ pub struct AnimalBehavior {
     pub llama_stomp_rate: f32,
     pub llama_stomp_range: f32,
     pub llama_stomp_force: f32,
+    pub obstacle_avoidance: f32,
+    pub hazard_avoidance: f32,
+    pub avoidance_range: f32,
+    pub stamina_drain: f32,
+    pub stamina_regen: f32,
+    pub stamina_recovery: f32,
+    pub exhausted_speed: f32,
+    pub bold_chance: f32,
+    pub timid_chance: f32,
+    pub stubborn_chance: f32,
+    pub temperament_spread: f32,
+    pub leader_chance: f32,
+    pub leader_weight: f32,
+    pub graze_slowdown: f32,
+    pub wander_chance: f32,
+    pub wander_range: f32,
+    pub wander_speed: f32,
+    pub wolf_speed: f32,
+    pub wolf_vision: f32,
+    pub wolf_kill_range: f32,
+    pub wolf_kill_rate: f32,
+    pub wolf_scare_radius: f32,
+    pub wolf_flee_time: f32,
+    pub llama_speed: f32,
+    pub llama_windup: f32,
+    pub bark_radius: f32,
+    pub bark_strength: f32,
+    pub bark_cooldown: f32,
+    pub bark_startle: f32,
+    pub sprint_speed: f32,
+    pub sprint_drain: f32,
+    pub sprint_regen: f32,
+    pub crouch_speed: f32,
+    pub crouch_fear: f32,
 }
 
 const DEFAULT_BEHAVIOR: AnimalBehavior = AnimalBehavior {
@@ -102,15 +172,55 @@ const DEFAULT_BEHAVIOR: AnimalBehavior = AnimalBehavior {
     llama_stomp_rate: 5.,
     llama_stomp_range: 32.,
     llama_stomp_force: 600.,
+    obstacle_avoidance: 1.0,
+    hazard_avoidance: 0.0,
+    avoidance_range: 8.0,
+    stamina_drain: 0.2,
+    stamina_regen: 0.25,
+    stamina_recovery: 0.5,
+    exhausted_speed: 8.0,
+    bold_chance: 0.15,
+    timid_chance: 0.2,
+    stubborn_chance: 0.1,
+    temperament_spread: 0.1,
+    leader_chance: 0.05,
+    leader_weight: 4.0,
+    graze_slowdown: 0.8,
+    wander_chance: 0.05,
+    wander_range: 3.0,
+    wander_speed: 6.0,
+    wolf_speed: 40.0,
+    wolf_vision: 80.0,
+    wolf_kill_range: 5.0,
+    wolf_kill_rate: 3.0,
+    wolf_scare_radius: 24.0,
+    wolf_flee_time: 3.0,
+    llama_speed: 12.0,
+    llama_windup: 1.0,
+    bark_radius: 40.0,
+    bark_strength: 400.0,
+    bark_cooldown: 3.0,
+    bark_startle: 0.5,
+    sprint_speed: 1.6,
+    sprint_drain: 0.35,
+    sprint_regen: 0.2,
+    crouch_speed: 0.5,
+    crouch_fear: 0.4,
 };
 
+impl Default for AnimalBehavior {
+    fn default() -> Self {
+        DEFAULT_BEHAVIOR
+    }
+}
+
 impl Default for &AnimalBehavior {
     fn default() -> Self {
         &DEFAULT_BEHAVIOR
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Tiles {
     Empty,
     Wall,
@@ -119,6 +229,7 @@ pub enum Tiles {
     Trap,
     Goal,
     Llama,
+    Wolf,
 }
 
 struct LayoutIterator<'a> {
@@ -163,6 +274,11 @@ impl Iterator for LayoutIterator<'_> {
                 self.advance_col();
                 Some(Ok((pos, Tiles::Llama)))
             }
+            Some(b'W') => {
+                let pos = Vec2::new(self.row as f32, self.col as f32) * TILE_SIZE;
+                self.advance_col();
+                Some(Ok((pos, Tiles::Wolf)))
+            }
             Some(b'G') => {
                 let pos = Vec2::new(self.row as f32, self.col as f32) * TILE_SIZE;
                 self.advance_col();
@@ -1,10 +1,12 @@
 use self::loader::LevelAsset;
 use crate::state::{AllowedState, GameState};
-use bevy::prelude::*;
+use bevy::{prelude::*, utils::HashMap};
+use serde::Deserialize;
 
 pub mod builder;
 pub mod loader;
 pub mod progress;
+pub mod tilemap;
 pub mod transistion;
 
 pub const TILE_SIZE: f32 = 8.;
@@ -19,6 +21,7 @@ impl Plugin for LevelPlugin {
             progress::LevelProgressPlugin,
         ));
         app.init_resource::<Score>();
+        app.init_resource::<tilemap::TileMap>();
     }
 }
 
@@ -134,11 +137,18 @@ impl Default for LevelBundle {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+pub enum LossCause {
+    Trap,
+    Wolf,
+}
+
 #[derive(Resource, Default)]
 pub struct Score {
     pub lost: usize,
     pub saved: usize,
     pub total_sheep : usize,
+    pub lost_to: HashMap<LossCause, usize>,
 }
 
 impl Score {
@@ -146,6 +156,16 @@ impl Score {
         self.lost = 0;
         self.saved = 0;
         self.total_sheep = 0;
+        self.lost_to.clear();
+    }
+
+    pub fn record_loss(&mut self, cause: LossCause, count: usize) {
+        self.lost += count;
+        *self.lost_to.entry(cause).or_default() += count;
+    }
+
+    pub fn lost_to(&self, cause: LossCause) -> usize {
+        self.lost_to.get(&cause).copied().unwrap_or_default()
     }
 }
 
@@ -1,6 +1,10 @@
 use bevy::prelude::*;
 
-use crate::{state::GameState, ui::Dialog};
+use crate::{
+    simulation::{SimulationSet, SimulationTick},
+    state::GameState,
+    ui::Dialog,
+};
 
 use super::{loader::LevelAsset, LevelLoaded, Score};
 
@@ -9,21 +13,38 @@ impl Plugin for LevelProgressPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<LevelWon>();
         app.add_event::<LevelLost>();
-        app.add_systems(Update, check_progress.run_if(in_state(GameState::Game)));
+        app.add_systems(
+            FixedUpdate,
+            check_progress
+                .run_if(in_state(GameState::Game))
+                .after(SimulationSet::Gameplay),
+        );
     }
 }
 
 #[derive(Component)]
 pub struct LevelOver;
 
+/// The tick and score the level was won on, taken inside the fixed step so
+/// replays can compare them.
 #[derive(Event)]
-pub struct LevelWon;
+pub struct LevelWon {
+    pub tick: u64,
+    pub saved: usize,
+    pub lost: usize,
+}
 
+/// Like `LevelWon`, for a lost level.
 #[derive(Event)]
-pub struct LevelLost;
+pub struct LevelLost {
+    pub tick: u64,
+    pub saved: usize,
+    pub lost: usize,
+}
 
 fn check_progress(
     score: Res<Score>,
+    tick: Res<SimulationTick>,
     level: Query<(Entity, &Handle<LevelAsset>), (With<LevelLoaded>, Without<LevelOver>)>,
     levels: Res<Assets<LevelAsset>>,
     mut cmd: Commands,
@@ -53,7 +74,11 @@ fn check_progress(
             "{} You escorted {:.0} % of the sheep to safty!",
             level.win, saved_percent
         );
-        win.send(LevelWon);
+        win.send(LevelWon {
+            tick: tick.0,
+            saved: score.saved,
+            lost: score.lost,
+        });
         cmd.entity(entity).insert(LevelOver);
     }
 
@@ -62,7 +87,11 @@ fn check_progress(
             "{} You lost {:.0} % of the sheep! Try again!",
             level.loose, lost_percent
         );
-        loose.send(LevelLost);
+        loose.send(LevelLost {
+            tick: tick.0,
+            saved: score.saved,
+            lost: score.lost,
+        });
         cmd.entity(entity).insert(LevelOver);
     }
 
@@ -0,0 +1,179 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use bevy::{prelude::*, utils::HashMap};
+
+use super::{loader::Tiles, TILE_SIZE};
+
+const STRAIGHT_COST: u32 = 10;
+const DIAGONAL_COST: u32 = 14;
+
+const NEIGHBOURS: [IVec2; 8] = [
+    IVec2::new(1, 0),
+    IVec2::new(-1, 0),
+    IVec2::new(0, 1),
+    IVec2::new(0, -1),
+    IVec2::new(1, 1),
+    IVec2::new(1, -1),
+    IVec2::new(-1, 1),
+    IVec2::new(-1, -1),
+];
+
+/// Tile lookup of the currently loaded level, indexed by tile coordinate.
+#[derive(Resource, Default)]
+pub struct TileMap {
+    tiles: HashMap<IVec2, Tiles>,
+}
+
+impl TileMap {
+    pub fn from_layout(layout: &[(Vec2, Tiles)]) -> Self {
+        Self {
+            tiles: layout
+                .iter()
+                .map(|(pos, tile)| (Self::coord(*pos), *tile))
+                .collect(),
+        }
+    }
+
+    pub fn coord(position: Vec2) -> IVec2 {
+        (position / TILE_SIZE).round().as_ivec2()
+    }
+
+    pub fn center(coord: IVec2) -> Vec2 {
+        coord.as_vec2() * TILE_SIZE
+    }
+
+    pub fn get(&self, coord: IVec2) -> Option<Tiles> {
+        self.tiles.get(&coord).copied()
+    }
+
+    /// Floor a sheep may walk onto on its own.
+    pub fn is_free(&self, coord: IVec2) -> bool {
+        matches!(
+            self.get(coord),
+            Some(Tiles::Empty | Tiles::Sheep | Tiles::Dog | Tiles::Llama | Tiles::Wolf)
+        )
+    }
+
+    /// Floor the dog may path through, it keeps clear of walls and traps.
+    pub fn is_walkable(&self, coord: IVec2) -> bool {
+        self.get(coord)
+            .is_some_and(|tile| !matches!(tile, Tiles::Wall | Tiles::Trap))
+    }
+
+    /// A* over the tiles in 8 directions, without cutting corners. Returns the
+    /// waypoints after the start tile, ending at `to`. If `to` can't be reached
+    /// the path leads as close to it as possible.
+    pub fn find_path(&self, from: Vec2, to: Vec2) -> Vec<Vec2> {
+        let start = Self::coord(from);
+        let goal = Self::coord(to);
+
+        let heuristic = |coord: IVec2| {
+            let delta = (goal - coord).abs();
+            let (short, long) = (delta.x.min(delta.y) as u32, delta.x.max(delta.y) as u32);
+            DIAGONAL_COST * short + STRAIGHT_COST * (long - short)
+        };
+
+        let mut open = BinaryHeap::new();
+        let mut came_from: HashMap<IVec2, IVec2> = HashMap::default();
+        let mut cost: HashMap<IVec2, u32> = HashMap::default();
+        let mut closest = (heuristic(start), start);
+
+        open.push(Reverse((heuristic(start), start.x, start.y)));
+        cost.insert(start, 0);
+
+        while let Some(Reverse((_, x, y))) = open.pop() {
+            let current = IVec2::new(x, y);
+            if current == goal {
+                closest = (0, goal);
+                break;
+            }
+
+            let current_cost = cost[&current];
+
+            for offset in NEIGHBOURS {
+                let next = current + offset;
+                if !self.is_walkable(next) {
+                    continue;
+                }
+
+                let diagonal = offset.x != 0 && offset.y != 0;
+                if diagonal
+                    && !(self.is_walkable(current + IVec2::new(offset.x, 0))
+                        && self.is_walkable(current + IVec2::new(0, offset.y)))
+                {
+                    continue;
+                }
+
+                let next_cost = current_cost
+                    + match diagonal {
+                        true => DIAGONAL_COST,
+                        false => STRAIGHT_COST,
+                    };
+
+                if cost.get(&next).is_some_and(|known| *known <= next_cost) {
+                    continue;
+                }
+
+                cost.insert(next, next_cost);
+                came_from.insert(next, current);
+                if heuristic(next) < closest.0 {
+                    closest = (heuristic(next), next);
+                }
+                open.push(Reverse((next_cost + heuristic(next), next.x, next.y)));
+            }
+        }
+
+        let (_, end) = closest;
+        let mut path = vec![];
+        let mut current = end;
+        while let Some(previous) = came_from.get(&current) {
+            path.push(Self::center(current));
+            current = *previous;
+        }
+        path.reverse();
+
+        if end == goal {
+            match path.last_mut() {
+                Some(last) => *last = to,
+                None => path.push(to),
+            }
+        }
+
+        path
+    }
+
+    /// Sum of push-away directions from all matching tiles within `range`,
+    /// weighted by closeness and clamped to unit length.
+    pub fn repulsion(&self, position: Vec2, range: f32, predicate: impl Fn(Tiles) -> bool) -> Vec2 {
+        let min = Self::coord(position - Vec2::splat(range + TILE_SIZE));
+        let max = Self::coord(position + Vec2::splat(range + TILE_SIZE));
+        let half = Vec2::splat(TILE_SIZE / 2.);
+
+        let mut push = Vec2::ZERO;
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                let coord = IVec2::new(x, y);
+                if !self.get(coord).is_some_and(&predicate) {
+                    continue;
+                }
+
+                let center = Self::center(coord);
+                let closest = position.clamp(center - half, center + half);
+                let distance = position.distance(closest);
+
+                if distance > range {
+                    continue;
+                }
+
+                let away = match distance > 0. {
+                    true => (position - closest) / distance,
+                    false => (position - center).normalize_or_zero(),
+                };
+
+                push += away * (1. - distance / range.max(f32::EPSILON));
+            }
+        }
+
+        push.clamp_length_max(1.)
+    }
+}
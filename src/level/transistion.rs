@@ -4,7 +4,8 @@ use bevy_nine_slice_ui::NineSliceUiTexture;
 use crate::{
     menu::LevelSelectorButton,
     state::{AllowedState, GameState},
-    ui::{DialogBoxTag, CreditsButton},
+    simulation::SimulationState,
+    ui::{DialogBoxTag, CreditsButton, RestartButton},
 };
 
 use super::{
@@ -13,10 +14,15 @@ use super::{
     progress::{LevelLost, LevelWon},
     Levels,
 };
+/// The retry and next level buttons shown once a level is over.
+#[derive(Component)]
+struct LevelOverMenu;
+
 pub struct LevelTransitionPlugin;
 impl Plugin for LevelTransitionPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Update, (retry_level, next_level, level_select_button));
+        app.add_systems(Update, restart_level.run_if(in_state(GameState::Game)));
         app.add_systems(OnEnter(GameState::Prepare), prepare_next_level);
     }
 }
@@ -25,6 +31,43 @@ fn prepare_next_level(mut events: EventWriter<LoadLevelEvent>, current_level: Re
     events.send(LoadLevelEvent::new(current_level.current()));
 }
 
+/// Tears the level down and builds it again from the already loaded asset.
+fn restart_level(
+    mut cmd: Commands,
+    inputs: Res<Input<KeyCode>>,
+    buttons: Query<&Interaction, (Changed<Interaction>, With<RestartButton>)>,
+    level: Query<(Entity, &Handle<LevelAsset>)>,
+    menus: Query<Entity, With<LevelOverMenu>>,
+    mut dialog_box: Query<&mut Visibility, With<DialogBoxTag>>,
+    mut events: EventWriter<LoadLevelEvent>,
+    mut simulation: ResMut<NextState<SimulationState>>,
+) {
+    let pressed = buttons
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed);
+
+    if !pressed && !inputs.just_pressed(KeyCode::R) {
+        return;
+    }
+
+    let Ok((entity, handle)) = level.get_single() else {
+        return;
+    };
+
+    events.send(LoadLevelEvent::new(handle.clone()));
+    cmd.entity(entity).despawn_recursive();
+
+    menus.iter().for_each(|menu| {
+        cmd.entity(menu).despawn_recursive();
+    });
+
+    dialog_box.iter_mut().for_each(|mut vis| {
+        *vis = Visibility::Hidden;
+    });
+
+    simulation.set(SimulationState::Running);
+}
+
 fn level_select_button(
     mut state: ResMut<NextState<GameState>>,
     mut current_level: ResMut<Levels>,
@@ -61,6 +104,7 @@ fn retry_level(
         },
         ..default()
     })
+    .insert(LevelOverMenu)
     .insert(AllowedState::new(GameState::Game))
     .with_children(|cmd| {
         spawn_progress_button("retry", current_level.current(), cmd, &server);
@@ -93,6 +137,7 @@ fn next_level(
         },
         ..default()
     })
+    .insert(LevelOverMenu)
     .insert(AllowedState::new(GameState::Game))
     .with_children(|cmd| {
         if let Some(next) = levels.next() {
@@ -19,12 +19,21 @@ use state::GameAssets;
 
 mod animals;
 mod camera;
+mod collision;
 mod controls;
 mod credits;
+mod death;
+mod ghost;
 mod goal;
+mod herding;
 mod level;
 mod menu;
+mod pause;
+mod replay;
+mod selection;
 mod settings;
+mod simulation;
+mod spatial;
 mod state;
 mod trap;
 mod ui;
@@ -59,7 +68,7 @@ fn main() {
             bevy_egui::EguiPlugin,
             #[cfg(debug_assertions)]
             WorldInspectorPlugin::default(),
-            RapierPhysicsPlugin::<()>::default(),
+            RapierPhysicsPlugin::<()>::default().in_fixed_schedule(),
             NineSliceUiPlugin::default(),
             controls::ControlPlugin,
             animals::SheepPlugin,
@@ -77,12 +86,16 @@ fn main() {
             goal::GoalPlugin,
             ui::UiPlugin,
             credits::CreditsPlugin,
+            selection::SelectionPlugin,
+            herding::HerdingPlugin,
+            death::DeathPlugin,
+            simulation::SimulationPlugin,
+            pause::PausePlugin,
+            replay::ReplayPlugin,
+            ghost::GhostPlugin,
         ))
         .add_systems(Startup, load)
-        .insert_resource(RapierConfiguration {
-            gravity: Vec2::ZERO,
-            ..default()
-        })
+        .insert_resource(simulation::rapier_configuration())
         .insert_resource(AmbientLight {
             color: Color::rgb_u8(194, 229, 156),
             brightness: 0.2,
@@ -116,6 +129,8 @@ fn load(mut cmd: Commands, mut game_assets: ResMut<GameAssets>, server: Res<Asse
     game_assets.add(dog_handle.clone().untyped());
     game_assets.add(llama_handle.clone().untyped());
     game_assets.add(server.load_folder("audio").untyped());
+    game_assets.add(server.load_folder("species").untyped());
+    game_assets.add(server.load_folder("deaths").untyped());
 
     cmd.insert_resource(Levels::new(vec![
         server.load("levels/1.level.ron"),
@@ -49,6 +49,9 @@ pub struct LevelSelectorButton(pub Handle<LevelAsset>);
 #[derive(Component)]
 pub struct LevelLoadButton;
 
+#[derive(Component)]
+pub struct DemoButton;
+
 #[derive(Event)]
 pub struct FileLoadedEvent {
     pub file: String,
@@ -332,13 +335,44 @@ fn spawn_menu(
                 });
             });
 
+            cmd.spawn(ButtonBundle {
+                style: Style {
+                    display: Display::Flex,
+                    width: Val::Px(200.),
+                    height: Val::Px(50.),
+                    margin: UiRect::vertical(Val::Px(10.)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                ..default()
+            })
+            .insert(DemoButton)
+            .insert(NineSliceUiTexture::from_slice(
+                server.load("sprites/ui.png"),
+                Rect::new(48., 0., 96., 48.),
+            ))
+            .with_children(|cmd| {
+                cmd.spawn(TextBundle {
+                    text: Text::from_section(
+                        "Demo",
+                        TextStyle {
+                            font_size: 20.,
+                            color: Color::WHITE,
+                            ..default()
+                        },
+                    ),
+                    ..default()
+                });
+            });
+
             cmd.spawn(TextBundle {
                 style: Style {
                             margin: UiRect::top(Val::Px(100.)),
                             ..default()
                         },
                 text: Text::from_section(
-                    "Right click to run, Mouswheel to zoom.\nBuild your own levels and share them! Link in the description",
+                    "Right click to run, Mouswheel to zoom, H lets Henk herd on his own, R restarts, Esc pauses, F fast-forwards.\nBuild your own levels and share them! Link in the description",
                     TextStyle {
                         font_size: 16.,
                         color: Color::WHITE,
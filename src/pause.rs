@@ -0,0 +1,209 @@
+use bevy::prelude::*;
+use bevy_nine_slice_ui::NineSliceUiTexture;
+
+use crate::{
+    settings::ShowSettings,
+    simulation::{GameSpeed, SimulationState},
+    state::{AllowedState, GameState},
+    ui::{BackToMenuButton, RestartButton},
+};
+
+pub struct PausePlugin;
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Game), spawn_speed_label);
+        app.add_systems(OnEnter(SimulationState::Paused), spawn_pause_overlay);
+        app.add_systems(OnExit(SimulationState::Paused), despawn_pause_overlay);
+        app.add_systems(
+            Update,
+            (
+                toggle_pause,
+                cycle_game_speed.run_if(in_state(SimulationState::Running)),
+                resume_button,
+                settings_button,
+            )
+                .run_if(in_state(GameState::Game)),
+        );
+        app.add_systems(
+            Update,
+            update_speed_label.run_if(resource_changed::<GameSpeed>()),
+        );
+    }
+}
+
+#[derive(Component)]
+struct PauseOverlay;
+
+#[derive(Component)]
+struct ResumeButton;
+
+#[derive(Component)]
+struct SettingsButton;
+
+#[derive(Component)]
+struct SpeedLabel;
+
+fn toggle_pause(
+    inputs: Res<Input<KeyCode>>,
+    state: Res<State<SimulationState>>,
+    mut next_state: ResMut<NextState<SimulationState>>,
+) {
+    if !inputs.just_pressed(KeyCode::Escape) {
+        return;
+    }
+
+    next_state.set(match state.get() {
+        SimulationState::Running => SimulationState::Paused,
+        SimulationState::Paused => SimulationState::Running,
+    });
+}
+
+fn cycle_game_speed(inputs: Res<Input<KeyCode>>, mut speed: ResMut<GameSpeed>) {
+    if inputs.just_pressed(KeyCode::F) {
+        *speed = speed.next();
+    }
+}
+
+fn resume_button(
+    query: Query<&Interaction, (Changed<Interaction>, With<ResumeButton>)>,
+    mut next_state: ResMut<NextState<SimulationState>>,
+) {
+    query.iter().for_each(|interaction| {
+        if *interaction == Interaction::Pressed {
+            next_state.set(SimulationState::Running);
+        }
+    });
+}
+
+fn settings_button(
+    query: Query<&Interaction, (Changed<Interaction>, With<SettingsButton>)>,
+    mut show: EventWriter<ShowSettings>,
+) {
+    query.iter().for_each(|interaction| {
+        if *interaction == Interaction::Pressed {
+            show.send(ShowSettings);
+        }
+    });
+}
+
+fn spawn_pause_overlay(mut cmd: Commands, server: Res<AssetServer>) {
+    cmd.spawn(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.),
+            height: Val::Percent(100.),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            flex_direction: FlexDirection::Column,
+            ..default()
+        },
+        background_color: Color::BLACK.with_a(0.5).into(),
+        z_index: ZIndex::Global(10),
+        ..default()
+    })
+    .insert(PauseOverlay)
+    .insert(AllowedState::new(GameState::Game))
+    .with_children(|cmd| {
+        cmd.spawn(TextBundle {
+            style: Style {
+                margin: UiRect::bottom(Val::Px(20.)),
+                ..default()
+            },
+            text: Text::from_section(
+                "Paused",
+                TextStyle {
+                    font_size: 40.,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ),
+            ..default()
+        });
+
+        spawn_button(cmd, &server, "Resume").insert(ResumeButton);
+        spawn_button(cmd, &server, "Restart").insert(RestartButton);
+        spawn_button(cmd, &server, "Settings").insert(SettingsButton);
+        spawn_button(cmd, &server, "Quit to Menu").insert(BackToMenuButton);
+    });
+}
+
+fn spawn_button<'w, 's, 'a>(
+    cmd: &'a mut ChildBuilder<'w, 's, '_>,
+    server: &AssetServer,
+    text: &str,
+) -> bevy::ecs::system::EntityCommands<'w, 's, 'a> {
+    let mut button = cmd.spawn(ButtonBundle {
+        style: Style {
+            display: Display::Flex,
+            width: Val::Px(200.),
+            height: Val::Px(50.),
+            margin: UiRect::vertical(Val::Px(5.)),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        ..default()
+    });
+
+    button
+        .insert(NineSliceUiTexture::from_slice(
+            server.load("sprites/ui.png"),
+            Rect::new(48., 0., 96., 48.),
+        ))
+        .with_children(|cmd| {
+            cmd.spawn(TextBundle {
+                text: Text::from_section(
+                    text,
+                    TextStyle {
+                        font_size: 20.,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                ),
+                ..default()
+            });
+        });
+
+    button
+}
+
+fn despawn_pause_overlay(mut cmd: Commands, query: Query<Entity, With<PauseOverlay>>) {
+    query.iter().for_each(|entity| {
+        cmd.entity(entity).despawn_recursive();
+    });
+}
+
+fn spawn_speed_label(mut cmd: Commands, speed: Res<GameSpeed>) {
+    cmd.spawn(TextBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            right: Val::Px(10.),
+            bottom: Val::Px(10.),
+            ..default()
+        },
+        text: Text::from_section(
+            speed_text(&speed),
+            TextStyle {
+                font_size: 20.,
+                color: Color::WHITE,
+                ..default()
+            },
+        ),
+        ..default()
+    })
+    .insert(SpeedLabel)
+    .insert(AllowedState::new(GameState::Game));
+}
+
+fn update_speed_label(mut query: Query<&mut Text, With<SpeedLabel>>, speed: Res<GameSpeed>) {
+    query.iter_mut().for_each(|mut text| {
+        text.sections[0].value = speed_text(&speed);
+    });
+}
+
+fn speed_text(speed: &GameSpeed) -> String {
+    match speed {
+        GameSpeed::Normal => String::new(),
+        speed => format!("{}x", speed.factor()),
+    }
+}
@@ -0,0 +1,284 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    controls::{apply_dog_input, DogCommand, DogInput},
+    herding::{BotMode, HerdingBot},
+    level::{
+        loader::LevelAsset,
+        progress::{LevelLost, LevelWon},
+        LevelLoaded, Levels,
+    },
+    simulation::{SimulationRng, SimulationSet, SimulationTick},
+    state::GameState,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+const REPLAY_DIR: &str = "replays";
+
+/// Records the player's commands of every level attempt and plays them back
+/// with `--replay <file>`. Together with the seed that reproduces the run.
+pub struct ReplayPlugin;
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Replays::from_args());
+        app.add_systems(OnEnter(GameState::Menu), start_playback);
+        app.add_systems(OnExit(GameState::Game), save_recording);
+        app.add_systems(
+            FixedUpdate,
+            (begin_level, play_input, record_input)
+                .chain()
+                .before(apply_dog_input)
+                .in_set(SimulationSet::Input),
+        );
+        app.add_systems(Update, level_over.run_if(in_state(GameState::Game)));
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Replay {
+    /// asset path, levels picked from disk in the menu have none
+    pub level: Option<String>,
+    pub seed: u64,
+    pub inputs: Vec<(u64, DogCommand)>,
+    /// how the recorded run ended, playback has to end the same way
+    #[serde(default)]
+    pub outcome: Option<Outcome>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Outcome {
+    pub tick: u64,
+    pub won: bool,
+    pub saved: usize,
+    pub lost: usize,
+}
+
+struct Playback {
+    replay: Replay,
+    cursor: usize,
+    started: bool,
+}
+
+#[derive(Resource, Default)]
+pub struct Replays {
+    playback: Option<Playback>,
+    recording: Option<Replay>,
+}
+
+impl Replays {
+    fn from_args() -> Self {
+        let mut args = std::env::args().skip_while(|arg| arg != "--replay");
+        let Some(path) = args.nth(1) else {
+            return Self::default();
+        };
+
+        let replay = std::fs::read_to_string(&path)
+            .map_err(|e| anyhow::anyhow!("{}", e))
+            .and_then(|text| ron::from_str::<Replay>(&text).map_err(|e| anyhow::anyhow!("{}", e)));
+
+        match replay {
+            Ok(replay) => Self {
+                playback: Some(Playback {
+                    replay,
+                    cursor: 0,
+                    started: false,
+                }),
+                recording: None,
+            },
+            Err(e) => {
+                error!("failed to read replay {}: {}", path, e);
+                Self::default()
+            }
+        }
+    }
+}
+
+fn start_playback(
+    mut replays: ResMut<Replays>,
+    mut levels: ResMut<Levels>,
+    mut rng: ResMut<SimulationRng>,
+    mut state: ResMut<NextState<GameState>>,
+    server: Res<AssetServer>,
+) {
+    let Some(playback) = replays
+        .playback
+        .as_mut()
+        .filter(|playback| !playback.started)
+    else {
+        return;
+    };
+
+    rng.seed_next_level(playback.replay.seed);
+
+    let Some(path) = playback.replay.level.clone() else {
+        info!("the replay was recorded on a level loaded from disk, pick it in the menu");
+        return;
+    };
+
+    info!("playing back {} with seed {}", path, playback.replay.seed);
+    levels.set(server.load(path));
+    state.set(GameState::Prepare);
+}
+
+fn begin_level(
+    mut replays: ResMut<Replays>,
+    level: Query<&Handle<LevelAsset>, Added<LevelLoaded>>,
+    server: Res<AssetServer>,
+    rng: Res<SimulationRng>,
+    bot: Res<HerdingBot>,
+) {
+    let Ok(handle) = level.get_single() else {
+        return;
+    };
+
+    // restarted before the level was over
+    if let Some(replay) = replays.recording.take() {
+        save(&replay);
+    }
+
+    match replays.playback.as_mut() {
+        Some(playback) if !playback.started => {
+            playback.started = true;
+            return;
+        }
+        // playback covers a single attempt, the next one is the player's again
+        Some(_) => replays.playback = None,
+        None => (),
+    }
+
+    if matches!(bot.mode, BotMode::Demo | BotMode::Verify) {
+        return;
+    }
+
+    replays.recording = Some(Replay {
+        level: server.get_path(handle.id()).map(|path| path.to_string()),
+        seed: rng.seed(),
+        inputs: Vec::new(),
+        outcome: None,
+    });
+}
+
+fn play_input(
+    mut replays: ResMut<Replays>,
+    mut input: ResMut<DogInput>,
+    tick: Res<SimulationTick>,
+) {
+    let Some(playback) = replays
+        .playback
+        .as_mut()
+        .filter(|playback| playback.started)
+    else {
+        return;
+    };
+
+    input.clear();
+
+    while let Some((at, command)) = playback.replay.inputs.get(playback.cursor) {
+        if *at > tick.0 {
+            break;
+        }
+
+        input.push(command.clone());
+        playback.cursor += 1;
+    }
+}
+
+fn record_input(mut replays: ResMut<Replays>, input: Res<DogInput>, tick: Res<SimulationTick>) {
+    let Some(replay) = replays.recording.as_mut() else {
+        return;
+    };
+
+    replay.inputs.extend(
+        input
+            .pending()
+            .iter()
+            .map(|command| (tick.0, command.clone())),
+    );
+}
+
+fn level_over(
+    mut replays: ResMut<Replays>,
+    mut won: EventReader<LevelWon>,
+    mut lost: EventReader<LevelLost>,
+) {
+    let won = won.read().last().map(|event| Outcome {
+        tick: event.tick,
+        won: true,
+        saved: event.saved,
+        lost: event.lost,
+    });
+    let lost = lost.read().last().map(|event| Outcome {
+        tick: event.tick,
+        won: false,
+        saved: event.saved,
+        lost: event.lost,
+    });
+
+    let Some(outcome) = won.or(lost) else {
+        return;
+    };
+
+    if let Some(playback) = replays
+        .playback
+        .as_ref()
+        .filter(|playback| playback.started)
+    {
+        info!("replay ended {:?}", outcome);
+
+        let left = playback.replay.inputs.len() - playback.cursor;
+        match playback.replay.outcome {
+            Some(recorded) if recorded != outcome => {
+                error!("replay diverged, recorded {:?}", recorded)
+            }
+            _ if left > 0 => error!("replay diverged, {} recorded inputs never played", left),
+            Some(_) => info!("replay matches the recording"),
+            None => warn!("replay has no recorded outcome to check against"),
+        }
+    }
+
+    if let Some(mut replay) = replays.recording.take() {
+        replay.outcome = Some(outcome);
+        save(&replay);
+    }
+}
+
+fn save_recording(mut replays: ResMut<Replays>) {
+    if let Some(replay) = replays.recording.take() {
+        save(&replay);
+    }
+}
+
+fn save(replay: &Replay) {
+    let text = match ron::ser::to_string_pretty(replay, ron::ser::PrettyConfig::default()) {
+        Ok(text) => text,
+        Err(e) => {
+            error!("failed to serialize replay: {}", e);
+            return;
+        }
+    };
+
+    #[cfg(target_arch = "wasm32")]
+    info!("replay:\n{}", text);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let name = replay
+            .level
+            .as_deref()
+            .and_then(|level| std::path::Path::new(level).file_name())
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.split('.').next())
+            .unwrap_or("custom");
+        let secs = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+        let path = std::path::Path::new(REPLAY_DIR).join(format!("{}-{}.replay.ron", name, secs));
+
+        match std::fs::create_dir_all(REPLAY_DIR).and_then(|_| std::fs::write(&path, text)) {
+            Ok(_) => info!("replay saved to {}", path.display()),
+            Err(e) => error!("failed to save replay {}: {}", path.display(), e),
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use bevy::prelude::*;
+
+use crate::{
+    animals::dog::DogTag,
+    camera::MainCamera,
+    controls::{intersect_ray_with_z_zero, MapClickEvent},
+    level::TILE_SIZE,
+    simulation::SimulationState,
+    state::GameState,
+};
+
+const HOTKEYS: [KeyCode; 9] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+];
+
+// cursor travel in pixels before a click turns into a box selection
+const DRAG_THRESHOLD: f32 = 6.;
+const PICK_RADIUS: f32 = TILE_SIZE;
+const RING_COLOR: Color = Color::rgb(1.0, 0.9, 0.2);
+
+pub struct SelectionPlugin;
+impl Plugin for SelectionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DragSelection>();
+        app.add_systems(
+            Update,
+            (
+                (select_dogs, select_by_hotkey).run_if(in_state(SimulationState::Running)),
+                draw_selection,
+            )
+                .run_if(in_state(GameState::Game)),
+        );
+    }
+}
+
+/// Dogs that receive orders.
+#[derive(Component, Default)]
+pub struct Selected;
+
+/// Spawn order of a dog, bound to the number keys.
+#[derive(Component, Default)]
+pub struct DogIndex(pub usize);
+
+/// Screen position where the left mouse button went down.
+#[derive(Resource, Default)]
+struct DragSelection(Option<Vec2>);
+
+fn select_dogs(
+    mut cmd: Commands,
+    mut clicks: EventReader<MapClickEvent>,
+    mut drag: ResMut<DragSelection>,
+    dogs: Query<(Entity, &GlobalTransform, Has<Selected>), With<DogTag>>,
+    camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    inputs: Res<Input<KeyCode>>,
+) {
+    let Ok((cam, cam_trans)) = camera.get_single() else {
+        return;
+    };
+
+    let additive = inputs.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+
+    clicks
+        .read()
+        .filter(|event| event.button() == MouseButton::Left)
+        .for_each(|event| {
+            if event.pressed() {
+                drag.0 = Some(event.screen_position());
+                return;
+            }
+
+            let Some(start) = drag.0.take() else {
+                return;
+            };
+
+            let is_click = start.distance(event.screen_position()) < DRAG_THRESHOLD;
+            let picked: Vec<Entity> = match is_click {
+                true => dogs
+                    .iter()
+                    .map(|(entity, transform, _)| {
+                        let distance = transform
+                            .translation()
+                            .truncate()
+                            .distance(event.translation().truncate());
+                        (entity, distance)
+                    })
+                    .filter(|(_, distance)| *distance < PICK_RADIUS)
+                    .min_by(|(_, a), (_, b)| a.total_cmp(b))
+                    .map(|(entity, _)| entity)
+                    .into_iter()
+                    .collect(),
+                false => {
+                    let rect = Rect::from_corners(start, event.screen_position());
+                    dogs.iter()
+                        .filter(|(_, transform, _)| {
+                            cam.world_to_viewport(cam_trans, transform.translation())
+                                .is_some_and(|position| rect.contains(position))
+                        })
+                        .map(|(entity, _, _)| entity)
+                        .collect()
+                }
+            };
+
+            // clicking into the void keeps the current selection
+            if picked.is_empty() {
+                return;
+            }
+
+            dogs.iter().for_each(|(entity, _, selected)| {
+                match (picked.contains(&entity), selected) {
+                    (true, _) => {
+                        cmd.entity(entity).insert(Selected);
+                    }
+                    (false, true) if !additive => {
+                        cmd.entity(entity).remove::<Selected>();
+                    }
+                    _ => (),
+                }
+            });
+        });
+}
+
+fn select_by_hotkey(
+    mut cmd: Commands,
+    dogs: Query<(Entity, &DogIndex), With<DogTag>>,
+    inputs: Res<Input<KeyCode>>,
+) {
+    let Some(index) = HOTKEYS.iter().position(|key| inputs.just_pressed(*key)) else {
+        return;
+    };
+
+    if !dogs.iter().any(|(_, dog_index)| dog_index.0 == index) {
+        return;
+    }
+
+    let additive = inputs.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+
+    dogs.iter().for_each(|(entity, dog_index)| {
+        if dog_index.0 == index {
+            cmd.entity(entity).insert(Selected);
+        } else if !additive {
+            cmd.entity(entity).remove::<Selected>();
+        }
+    });
+}
+
+fn draw_selection(
+    mut gizmos: Gizmos,
+    selected: Query<&GlobalTransform, (With<DogTag>, With<Selected>)>,
+    drag: Res<DragSelection>,
+    camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    windows: Query<&Window>,
+) {
+    selected.iter().for_each(|transform| {
+        let position = transform.translation().truncate().extend(0.2);
+        gizmos.circle(position, Vec3::Z, 3.5, RING_COLOR);
+    });
+
+    let Some(start) = drag.0 else {
+        return;
+    };
+
+    let Ok((cam, cam_trans)) = camera.get_single() else {
+        return;
+    };
+
+    let Some(cursor) = windows.iter().find_map(|window| window.cursor_position()) else {
+        return;
+    };
+
+    if start.distance(cursor) < DRAG_THRESHOLD {
+        return;
+    }
+
+    // project the screen space box onto the ground
+    let corners: Option<Vec<Vec3>> = [
+        start,
+        Vec2::new(cursor.x, start.y),
+        cursor,
+        Vec2::new(start.x, cursor.y),
+        start,
+    ]
+    .into_iter()
+    .map(|corner| {
+        let ray = cam.viewport_to_world(cam_trans, corner)?;
+        intersect_ray_with_z_zero(ray.origin, ray.direction).map(|pos| pos + Vec3::Z * 0.2)
+    })
+    .collect();
+
+    if let Some(corners) = corners {
+        gizmos.linestrip(corners, RING_COLOR);
+    }
+}
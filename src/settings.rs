@@ -12,10 +12,15 @@ use crate::BackgroundMusic;
 pub struct SettingsPlugin;
 impl Plugin for SettingsPlugin {
     fn build(&self, app: &mut App) {
+        app.add_event::<ShowSettings>();
         app.add_systems(Update, (settings, adjust_background_music));
     }
 }
 
+/// Unfolds the settings window.
+#[derive(Event)]
+pub struct ShowSettings;
+
 #[derive(Resource)]
 pub struct GameSettings {
     pub music: f32,
@@ -39,7 +44,20 @@ fn settings(
     mut query: Query<&mut PointLight>,
     mut contexts: EguiContexts,
     mut settings: ResMut<GameSettings>,
+    mut show: EventReader<ShowSettings>,
 ) {
+    if show.read().count() > 0 {
+        // egui keeps the folded state of a window under its title
+        let id = egui::Id::new("Settings").with("collapsing");
+        let mut state = egui::collapsing_header::CollapsingState::load_with_default_open(
+            contexts.ctx_mut(),
+            id,
+            false,
+        );
+        state.set_open(true);
+        state.store(contexts.ctx_mut());
+    }
+
     egui::Window::new("Settings")
         .anchor(Align2::RIGHT_TOP, [1., 1.])
         .default_open(false)
@@ -0,0 +1,273 @@
+use bevy::{prelude::*, transform::TransformSystem};
+use bevy_rapier2d::plugin::{PhysicsSet, RapierConfiguration, TimestepMode};
+use rand::{rngs::StdRng, RngCore, SeedableRng};
+
+use crate::state::GameState;
+
+/// Gameplay and physics ticks per second, the same on every machine.
+pub const TICK_RATE: f64 = 60.;
+
+pub struct SimulationPlugin;
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Time::<Fixed>::from_hz(TICK_RATE));
+        app.init_resource::<SimulationTick>();
+        app.init_resource::<SimulationRng>();
+        app.init_resource::<GameSpeed>();
+        app.add_state::<SimulationState>();
+        app.add_systems(OnEnter(SimulationState::Paused), pause_time);
+        app.add_systems(OnEnter(SimulationState::Running), resume_time);
+        app.add_systems(OnExit(GameState::Game), reset_simulation);
+        app.add_systems(OnEnter(GameState::Game), apply_game_speed);
+        app.add_systems(
+            Update,
+            apply_game_speed
+                .run_if(in_state(GameState::Game).and_then(resource_changed::<GameSpeed>())),
+        );
+        app.configure_sets(
+            FixedUpdate,
+            (
+                SimulationSet::Restore,
+                SimulationSet::Prepare,
+                SimulationSet::Input,
+                SimulationSet::Gameplay,
+                PhysicsSet::SyncBackend,
+            )
+                .chain(),
+        );
+        app.configure_sets(
+            FixedUpdate,
+            SimulationSet::Record.after(PhysicsSet::Writeback),
+        );
+        app.add_systems(
+            FixedUpdate,
+            (restore_transforms, advance_tick).in_set(SimulationSet::Restore),
+        );
+        app.add_systems(FixedUpdate, record_transforms.in_set(SimulationSet::Record));
+        app.add_systems(
+            PostUpdate,
+            interpolate_transforms.before(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+/// Rapier config stepping exactly once per fixed tick.
+pub fn rapier_configuration() -> RapierConfiguration {
+    RapierConfiguration {
+        gravity: Vec2::ZERO,
+        timestep_mode: TimestepMode::Fixed {
+            dt: (1. / TICK_RATE) as f32,
+            substeps: 1,
+        },
+        ..default()
+    }
+}
+
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SimulationSet {
+    /// puts simulated entities back where the last tick left them
+    Restore,
+    /// snapshots shared by the gameplay systems, like the spatial grid
+    Prepare,
+    /// player commands land here, live or from a replay
+    Input,
+    Gameplay,
+    /// after physics, remembers where everything ended up for rendering
+    Record,
+}
+
+/// Paused freezes virtual time, which stops the fixed ticks, every timer
+/// and all animations at once.
+#[derive(States, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SimulationState {
+    #[default]
+    Running,
+    Paused,
+}
+
+/// Fast-forward for slow levels, only applies while playing.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GameSpeed {
+    #[default]
+    Normal,
+    Double,
+    Quadruple,
+}
+
+impl GameSpeed {
+    pub fn factor(&self) -> f32 {
+        match self {
+            Self::Normal => 1.,
+            Self::Double => 2.,
+            Self::Quadruple => 4.,
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            Self::Normal => Self::Double,
+            Self::Double => Self::Quadruple,
+            Self::Quadruple => Self::Normal,
+        }
+    }
+}
+
+/// Fixed ticks simulated since the level was built.
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SimulationTick(pub u64);
+
+/// Seeded random numbers for everything that changes how a level plays out.
+/// Effects and sounds don't need it.
+#[derive(Resource)]
+pub struct SimulationRng {
+    seed: u64,
+    next_seed: Option<u64>,
+    rng: StdRng,
+}
+
+impl Default for SimulationRng {
+    fn default() -> Self {
+        let seed = rand::random();
+        Self {
+            seed,
+            next_seed: None,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl SimulationRng {
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Plays the next level with this seed instead of a fresh one.
+    pub fn seed_next_level(&mut self, seed: u64) {
+        self.next_seed = Some(seed);
+    }
+
+    pub fn start_level(&mut self) {
+        self.seed = self.next_seed.take().unwrap_or_else(rand::random);
+        self.rng = StdRng::seed_from_u64(self.seed);
+    }
+
+    pub fn rng(&mut self) -> &mut StdRng {
+        &mut self.rng
+    }
+}
+
+/// Random numbers hashed from a seed and the tick, for the parallel systems
+/// that can't share the `SimulationRng`.
+pub struct HashRng(u64);
+
+impl HashRng {
+    pub fn new(seed: u64, tick: u64) -> Self {
+        Self(seed ^ tick.wrapping_mul(0x9E37_79B9_7F4A_7C15))
+    }
+}
+
+impl RngCore for HashRng {
+    // splitmix64
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        dest.chunks_mut(8).for_each(|chunk| {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        });
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// Rendered in between the last two simulated positions, so motion stays
+/// smooth when the framerate and the tick rate don't line up. Only the
+/// planar part is touched, height belongs to tweens and effects.
+#[derive(Component, Default)]
+pub struct Interpolated {
+    previous: Option<(Vec2, Quat)>,
+    current: Option<(Vec2, Quat)>,
+}
+
+fn pause_time(mut time: ResMut<Time<Virtual>>) {
+    time.pause();
+}
+
+fn resume_time(mut time: ResMut<Time<Virtual>>) {
+    time.unpause();
+}
+
+fn apply_game_speed(mut time: ResMut<Time<Virtual>>, speed: Res<GameSpeed>) {
+    time.set_relative_speed(speed.factor());
+}
+
+fn reset_simulation(
+    mut time: ResMut<Time<Virtual>>,
+    mut next_state: ResMut<NextState<SimulationState>>,
+) {
+    // menus and transitions always run at normal speed
+    time.set_relative_speed(1.);
+    next_state.set(SimulationState::Running);
+}
+
+fn advance_tick(mut tick: ResMut<SimulationTick>) {
+    tick.0 += 1;
+}
+
+fn restore_transforms(mut query: Query<(&Interpolated, &mut Transform, &mut GlobalTransform)>) {
+    query
+        .iter_mut()
+        .for_each(|(interpolated, mut transform, mut global)| {
+            let Some((position, rotation)) = interpolated.current else {
+                return;
+            };
+
+            transform.translation = position.extend(transform.translation.z);
+            transform.rotation = rotation;
+            // rapier syncs bodies from the global transform, which still holds
+            // the interpolated one from the last frame. Animals sit directly
+            // below the level root at the origin.
+            *global = GlobalTransform::from(*transform);
+        });
+}
+
+fn record_transforms(mut query: Query<(&mut Interpolated, &Transform)>) {
+    query.iter_mut().for_each(|(mut interpolated, transform)| {
+        let now = (transform.translation.truncate(), transform.rotation);
+        interpolated.previous = interpolated.current.or(Some(now));
+        interpolated.current = Some(now);
+    });
+}
+
+fn interpolate_transforms(
+    mut query: Query<(&Interpolated, &mut Transform)>,
+    fixed: Res<Time<Fixed>>,
+) {
+    let blend = fixed.overstep_percentage();
+
+    query.iter_mut().for_each(|(interpolated, mut transform)| {
+        let (Some((previous, previous_rotation)), Some((current, current_rotation))) =
+            (interpolated.previous, interpolated.current)
+        else {
+            return;
+        };
+
+        transform.translation = previous
+            .lerp(current, blend)
+            .extend(transform.translation.z);
+        transform.rotation = previous_rotation.slerp(current_rotation, blend);
+    });
+}
@@ -0,0 +1,86 @@
+use bevy::{prelude::*, utils::HashMap};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GridKind {
+    Sheep,
+    Dog,
+    Predator,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct GridEntry {
+    pub entity: Entity,
+    pub kind: GridKind,
+    pub position: Vec2,
+    pub velocity: Vec2,
+    /// how much this entry counts when neighbours are averaged
+    pub weight: f32,
+}
+
+/// Uniform grid over all animals. Rebuilt once per tick, so every system
+/// reading it during the tick sees the same snapshot.
+#[derive(Resource)]
+pub struct SpatialGrid {
+    cell_size: f32,
+    cells: HashMap<IVec2, Vec<GridEntry>>,
+}
+
+impl Default for SpatialGrid {
+    fn default() -> Self {
+        Self::new(20.)
+    }
+}
+
+impl SpatialGrid {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size: cell_size.max(1.),
+            cells: HashMap::default(),
+        }
+    }
+
+    /// Empties the grid but keeps the cell allocations around for the next rebuild.
+    pub fn clear(&mut self, cell_size: f32) {
+        let cell_size = cell_size.max(1.);
+        if cell_size != self.cell_size {
+            self.cell_size = cell_size;
+            self.cells.clear();
+            return;
+        }
+
+        self.cells.values_mut().for_each(Vec::clear);
+    }
+
+    pub fn insert(&mut self, entry: GridEntry) {
+        let cell = self.cell(entry.position);
+        self.cells.entry(cell).or_default().push(entry);
+    }
+
+    /// All entries within `radius` of `position`.
+    pub fn query(&self, position: Vec2, radius: f32) -> impl Iterator<Item = &GridEntry> + '_ {
+        let min = self.cell(position - Vec2::splat(radius));
+        let max = self.cell(position + Vec2::splat(radius));
+        let radius_squared = radius * radius;
+
+        (min.x..=max.x)
+            .flat_map(move |x| (min.y..=max.y).map(move |y| IVec2::new(x, y)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .filter(move |entry| entry.position.distance_squared(position) <= radius_squared)
+    }
+
+    /// Entries of one kind within `radius` of `position`.
+    pub fn query_kind(
+        &self,
+        position: Vec2,
+        radius: f32,
+        kind: GridKind,
+    ) -> impl Iterator<Item = &GridEntry> + '_ {
+        self.query(position, radius)
+            .filter(move |entry| entry.kind == kind)
+    }
+
+    fn cell(&self, position: Vec2) -> IVec2 {
+        (position / self.cell_size).floor().as_ivec2()
+    }
+}
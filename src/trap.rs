@@ -1,27 +1,27 @@
-use std::time::Duration;
-
 use bevy::{
     audio::{PlaybackMode, Volume, VolumeLevel},
     prelude::*, utils::HashSet,
 };
 use bevy_rapier2d::{
-    dynamics::Velocity,
-    geometry::{Collider, Sensor},
+    geometry::{Collider, CollisionGroups, Sensor},
     plugin::RapierContext,
 };
 
 use crate::{
-    animals::{animations::AnimalState, sheep::SheepTag},
-    level::Score,
-    util::LifeTime,
+    collision,
+    death::kill,
+    level::{LossCause, Score},
+    simulation::SimulationSet,
     GameSettings,
 };
-
-use super::goal::{FAIL_GLOW, GLOW_MESH};
 pub struct TrapPlugin;
 impl Plugin for TrapPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, (watch_trap_enter, update_emission));
+        app.add_systems(
+            FixedUpdate,
+            watch_trap_enter.in_set(SimulationSet::Gameplay),
+        );
+        app.add_systems(Update, update_emission);
     }
 }
 
@@ -44,6 +44,7 @@ pub struct TrapBundle {
     pub name: Name,
     pub sensor: Sensor,
     pub collider: Collider,
+    pub collision_groups: CollisionGroups,
 }
 
 impl Default for TrapBundle {
@@ -59,6 +60,7 @@ impl Default for TrapBundle {
             global_transform: GlobalTransform::default(),
             sensor: Sensor,
             collider: Collider::default(),
+            collision_groups: collision::sensor(),
             name: Name::new("trap"),
         }
     }
@@ -87,7 +89,6 @@ fn watch_trap_enter(
     mut score: ResMut<Score>,
     _meshes: ResMut<Assets<Mesh>>,
     goals: Query<Entity, With<TrapTag>>,
-    sheeps: Query<Entity, With<SheepTag>>,
     rapier_context: Res<RapierContext>,
     server: Res<AssetServer>,
     death_sound: Query<With<DeathSound>>,
@@ -97,26 +98,15 @@ fn watch_trap_enter(
     goals.iter().for_each(|entity| {
         rapier_context
             .intersections_with(entity)
+            .filter(|(_, _, intersecting)| *intersecting)
             .for_each(|(a, b, _)| {
+                // the sensor layer only ever intersects with sheep
                 let sheep_ent = if a == entity { b } else { a };
-                if sheeps.get(sheep_ent).is_err() {
+                if !dying_sheeps.insert(sheep_ent) {
                     return;
                 }
 
-
-                dying_sheeps.insert(sheep_ent);
-                cmd.entity(sheep_ent)
-                    .insert(LifeTime::new(Duration::from_millis(500)))
-                    .insert(AnimalState::Dead)
-                    .remove::<Velocity>()
-                    .remove::<SheepTag>()
-                    .with_children(|cmd| {
-                        cmd.spawn(MaterialMeshBundle {
-                            mesh: GLOW_MESH.clone(),
-                            material: FAIL_GLOW.clone(),
-                            ..default()
-                        });
-                    });
+                kill(&mut cmd, sheep_ent, LossCause::Trap);
 
                 if death_sound.iter().count() > 2 {
                     return;
@@ -134,5 +124,5 @@ fn watch_trap_enter(
                 .insert(DeathSound);
             })
     });
-    score.lost += dying_sheeps.len();
+    score.record_loss(LossCause::Trap, dying_sheeps.len());
 }
@@ -1,7 +1,7 @@
 use std::time::Duration;
 
 use crate::{
-    level::{Score, Levels},
+    level::{LossCause, Score, Levels},
     state::{AllowedState, GameState},
     util::VisibilityTimer,
 };
@@ -43,6 +43,9 @@ struct PortraitRender(Handle<Image>);
 #[derive(Component)]
 pub struct BackToMenuButton;
 
+#[derive(Component)]
+pub struct RestartButton;
+
 #[derive(Component)]
 pub struct HideDialogButton;
 
@@ -73,6 +76,11 @@ fn update_ui(
         let percent_saved = game_score.saved as f32 / game_score.total_sheep as f32;
 
         text.sections[0].value = format!("Lost: {:.0} %  Escorted {:.0} %  Level {}/4", percent_lost * 100., percent_saved * 100., levels.current_index() + 1);
+
+        let lost_to_wolves = game_score.lost_to(LossCause::Wolf);
+        if lost_to_wolves > 0 {
+            text.sections[0].value += &format!("  Eaten by wolves: {}", lost_to_wolves);
+        }
     });
 }
 
@@ -178,6 +186,34 @@ fn spawn_ui(mut cmd: Commands, portrait: Res<PortraitRender>, server: Res<AssetS
                     ..default()
                 });
             });
+            cmd.spawn(ButtonBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(150.),
+                    bottom: Val::Percent(2.),
+                    padding: UiRect::all(Val::Px(10.)),
+                    ..default()
+                },
+                ..default()
+            })
+            .insert(RestartButton)
+            .insert(NineSliceUiTexture::from_slice(
+                server.load("sprites/ui.png"),
+                Rect::new(48., 0., 96., 48.),
+            ))
+            .with_children(|cmd| {
+                cmd.spawn(TextBundle {
+                    text: Text::from_section(
+                        "Restart (R)",
+                        TextStyle {
+                            font_size: 16.,
+                            color: Color::WHITE,
+                            ..default()
+                        },
+                    ),
+                    ..default()
+                });
+            });
             cmd.spawn(NineSliceUiMaterialBundle {
                 style: Style {
                     display: Display::Flex,
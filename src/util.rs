@@ -1,13 +1,15 @@
-use std::time::Duration;
+use std::{hash::Hash, time::Duration};
+
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::simulation::SimulationSet;
 
-use bevy::prelude::*;
 pub struct UtilPlugin;
 impl Plugin for UtilPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            Update,
-            (lifetime_system, cooldown_system, visibility_timer_system),
-        );
+        app.add_systems(Update, (lifetime_system, visibility_timer_system));
+        // cooldowns gate gameplay, so they run down with the simulation
+        app.add_systems(FixedUpdate, cooldown_system.in_set(SimulationSet::Gameplay));
     }
 }
 
@@ -40,6 +42,53 @@ impl Cooldown {
     }
 }
 
+/// New materials of newly spawned scenes, see `restyle_new_materials`.
+pub type NewMaterials<'w, 's> =
+    Query<'w, 's, (Entity, &'static mut Handle<StandardMaterial>), Added<Handle<StandardMaterial>>>;
+
+/// Swaps the materials showing up below an entity with a `T` for restyled
+/// copies. Scenes spawn a few frames after their entity, so this has to run
+/// every frame and pick up the meshes as they come. Copies are shared by
+/// everything with the same original material and `key`, `None` leaves the
+/// material alone.
+pub fn restyle_new_materials<T: Component, K: Eq + Hash>(
+    handles: &mut NewMaterials,
+    parents: &Query<&Parent>,
+    owners: &Query<&T>,
+    materials: &mut Assets<StandardMaterial>,
+    cache: &mut HashMap<(AssetId<StandardMaterial>, K), Handle<StandardMaterial>>,
+    key: impl Fn(&T) -> Option<K>,
+    restyle: impl Fn(&T, &mut StandardMaterial),
+) {
+    handles.iter_mut().for_each(|(entity, mut handle)| {
+        let Some(owner) = parents
+            .iter_ancestors(entity)
+            .find_map(|ancestor| owners.get(ancestor).ok())
+        else {
+            return;
+        };
+
+        let Some(key) = key(owner) else {
+            return;
+        };
+
+        let key = (handle.id(), key);
+        if let Some(restyled) = cache.get(&key) {
+            *handle = restyled.clone();
+            return;
+        }
+
+        let Some(mut material) = materials.get(handle.as_ref()).cloned() else {
+            return;
+        };
+        restyle(owner, &mut material);
+
+        let restyled = materials.add(material);
+        cache.insert(key, restyled.clone());
+        *handle = restyled;
+    });
+}
+
 fn lifetime_system(mut cmd: Commands, mut life_q: Query<(Entity, &mut LifeTime)>, time: Res<Time>) {
     life_q.iter_mut().for_each(|(ent, mut lifetime)| {
         lifetime.0.tick(time.delta()).finished().then(|| {